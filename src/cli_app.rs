@@ -1,76 +1,183 @@
 // cli_app.rs
 use gilrs::Gilrs; // Gilrs is used here for initial discovery
 use std::{
+    io::{self, Write},
+    path::PathBuf,
     sync::Arc,
     sync::atomic::{AtomicBool, Ordering},
+    sync::mpsc::{self, Receiver},
     thread::{self, JoinHandle},
 };
 
 // Assuming these modules are accessible. Adjust paths if necessary.
 use crate::{
     config::AppConfig,
-    poller_worker::{PollerConfig, PollerWorker}, // Import new structs
+    error::StartError,
+    gamepad::{GilrsSource, list_gamepads},
+    output::OutputConfig,
+    poller_worker::{PollerConfig, PollerWorker, WorkerCommand, WorkerStatus}, // Import new structs
 };
 
 /// CLI application now primarily manages the PollerWorker thread.
 pub struct CliApp {
     running_signal: Arc<AtomicBool>, // Signal for the worker to stop
     worker_handle: Option<JoinHandle<()>>, // To join the worker thread
+    status_rx: Receiver<WorkerStatus>, // Status updates from the worker
 }
 
 impl CliApp {
-    /// Initialize Gilrs, discover gamepad, configure and spawn PollerWorker.
-    pub fn new() -> Self {
-        let app_cfg = AppConfig::load().unwrap_or_default();
-
-        // Initialize Gilrs to find the active gamepad.
-        // This Gilrs instance will be moved to the PollerWorker.
-        let gilrs_instance = Gilrs::new().expect("Failed to initialize Gilrs");
-        let (active_id, gamepad) = gilrs_instance
-            .gamepads()
-            .next()
-            .expect("No gamepad connected. Please connect a gamepad and try again.");
+    /// Initialize Gilrs, configure and spawn PollerWorker.
+    /// The worker itself waits for (and follows) the configured gamepad.
+    /// With `record_session`, every packet sent is also written to that file.
+    /// `output` overrides the configured output for this run.
+    pub fn new(
+        record_session: Option<PathBuf>,
+        output: Option<OutputConfig>,
+    ) -> Result<Self, StartError> {
+        let mut app_cfg = AppConfig::load().unwrap_or_default();
+        if let Some(output) = output {
+            app_cfg.output = output;
+        }
+
+        // Initialize Gilrs; this instance will be moved to the PollerWorker.
+        let gilrs_instance = Gilrs::new()?;
 
         println!("3DS Input Redirection - CLI by lolxnn and contributors");
         println!("----------------------------------------");
-        println!("Using gamepad '{}' (id {:?})", gamepad.name(), active_id);
+        println!("Gamepad: {}", app_cfg.gamepad);
         println!("Target IP: {}", app_cfg.target_ip);
+        println!("Output: {}", app_cfg.output);
+        println!("Send rate: {} Hz", app_cfg.send_rate_hz);
         println!("LStick Deadzone: {}", app_cfg.deadzone_lstick);
         println!("RStick Deadzone: {}", app_cfg.deadzone_rstick);
+        println!(
+            "LStick Outer Deadzone: {} ({:?})",
+            app_cfg.outer_deadzone_lstick, app_cfg.deadzone_shape_lstick
+        );
+        println!(
+            "RStick Outer Deadzone: {} ({:?})",
+            app_cfg.outer_deadzone_rstick, app_cfg.deadzone_shape_rstick
+        );
+        println!(
+            "LStick Response: {:?} x{}",
+            app_cfg.curve_lstick, app_cfg.sensitivity_lstick
+        );
+        println!(
+            "RStick Response: {:?} x{}",
+            app_cfg.curve_rstick, app_cfg.sensitivity_rstick
+        );
+        println!(
+            "Trigger Thresholds: press {}, release {}",
+            app_cfg.trigger_press_threshold, app_cfg.trigger_release_threshold
+        );
+        match app_cfg.stick_to_dpad {
+            Some(emu) => println!(
+                "Stick to D-pad: {:?} stick, {:?}-way, threshold {}",
+                emu.stick, emu.ways, emu.threshold
+            ),
+            None => println!("Stick to D-pad: off"),
+        }
+        println!("D-pad to Circle Pad: {}", app_cfg.dpad_to_circle_pad);
+        for (button, turbo) in &app_cfg.turbo_buttons {
+            println!(
+                "Turbo {button:?}: {} Hz, {}% down{}",
+                turbo.rate_hz,
+                (turbo.duty_cycle * 100.0).round(),
+                if turbo.enabled { "" } else { " (disabled)" }
+            );
+        }
+        if !app_cfg.turbo_toggle_chord.is_empty() {
+            println!("Turbo Toggle Chord: {:?}", app_cfg.turbo_toggle_chord);
+        }
+        for binding in &app_cfg.macros {
+            println!(
+                "Macro '{}': chord {:?}{}{}",
+                binding.name,
+                binding.chord,
+                if binding.looped { ", looped" } else { "" },
+                if binding.cancel_on_input {
+                    ", cancel on input"
+                } else {
+                    ""
+                }
+            );
+        }
+        for hotspot in &app_cfg.touch_hotspots {
+            let bound: Vec<_> = app_cfg
+                .button_map
+                .0
+                .keys()
+                .filter(|b| app_cfg.button_map.hotspots(b).any(|n| n == hotspot.name))
+                .collect();
+            println!(
+                "Touch Hotspot '{}': ({}, {}), {}, bound to {bound:?}",
+                hotspot.name,
+                hotspot.x,
+                hotspot.y,
+                match hotspot.hold_ms {
+                    Some(ms) => format!("tap {ms} ms"),
+                    None => "held".to_string(),
+                }
+            );
+        }
         println!("Invert LStick X: {}", app_cfg.invert_lx);
         println!("Invert LStick Y: {}", app_cfg.invert_ly);
         println!("Invert RStick X: {}", app_cfg.invert_rx);
         println!("Invert RStick Y: {}", app_cfg.invert_ry);
 
-        let poller_config = PollerConfig {
-            target_ip: app_cfg.target_ip.clone(),
-            deadzone_lstick: app_cfg.deadzone_lstick,
-            deadzone_rstick: app_cfg.deadzone_rstick,
-            invert_lx: app_cfg.invert_lx,
-            invert_ly: app_cfg.invert_ly,
-            invert_rx: app_cfg.invert_rx,
-            invert_ry: app_cfg.invert_ry,
-        };
+        let poller_config = PollerConfig::from(&app_cfg);
 
         let running_signal = Arc::new(AtomicBool::new(true));
+        let (status_tx, status_rx) = mpsc::channel();
 
         // Create the PollerWorker instance, moving the gilrs instance and passing config.
         let mut poller_worker = PollerWorker::new(
-            gilrs_instance, // Gilrs instance is moved here
-            active_id,
+            vec![Box::new(GilrsSource::new(
+                gilrs_instance, // Gilrs instance is moved here
+                app_cfg.gamepad.clone(),
+            ))],
             poller_config,
             running_signal.clone(),
-        );
+            status_tx,
+        )?;
+
+        if let Some(path) = record_session {
+            let _ = poller_worker
+                .commands()
+                .send(WorkerCommand::StartSession(path));
+        }
 
         // Spawn the PollerWorker in a new thread.
         let worker_handle = thread::spawn(move || {
             poller_worker.run();
         });
 
-        CliApp {
+        Ok(CliApp {
             running_signal,
             worker_handle: Some(worker_handle),
+            status_rx,
+        })
+    }
+
+    /// Prints the connected gamepads and how to select one in the config.
+    pub fn list_gamepads() -> Result<(), StartError> {
+        let gilrs_instance = Gilrs::new()?;
+        let gamepads = list_gamepads(&gilrs_instance);
+        if gamepads.is_empty() {
+            println!("No gamepads connected.");
+            return Ok(());
+        }
+
+        println!("Connected gamepads:");
+        for info in &gamepads {
+            println!("  #{}  {}  (UUID {})", info.index, info.name, info.uuid);
         }
+        println!();
+        println!("Select one in config.toml with one of:");
+        println!("  gamepad = {{ Index = 0 }}");
+        println!("  gamepad = {{ Name = \"{}\" }}", gamepads[0].name);
+        println!("  gamepad = {{ Uuid = \"{}\" }}", gamepads[0].uuid);
+        Ok(())
     }
 
     /// Main application execution: sets up Ctrl+C handler and waits for PollerWorker.
@@ -87,6 +194,31 @@ impl CliApp {
         println!("CLI app running. Input-Redirection is active in a separate thread.");
         println!("Press Ctrl+C to stop.");
 
+        // Print status updates until the worker exits and drops its end of the channel.
+        // Statistics overwrite a single line; every other update gets its own line.
+        let mut stats_line_open = false;
+        for status in self.status_rx.iter() {
+            match status {
+                WorkerStatus::Stats {
+                    packets_per_sec, ..
+                } => {
+                    print!("\rInput-Redirection: {packets_per_sec} packets/s   ");
+                    let _ = io::stdout().flush();
+                    stats_line_open = true;
+                }
+                other => {
+                    if stats_line_open {
+                        println!();
+                        stats_line_open = false;
+                    }
+                    println!("Input-Redirection: {other}");
+                }
+            }
+        }
+        if stats_line_open {
+            println!();
+        }
+
         // Wait for the PollerWorker thread to complete its execution.
         if let Some(handle) = self.worker_handle.take() {
             match handle.join() {
@@ -1,16 +1,63 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-use std::{fs, io, path::PathBuf};
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use crate::{
+    dpad::StickToDpad,
+    gamepad::GamepadSelector,
+    keyboard::KeyMap,
+    macros::MacroBinding,
+    mapping::{ButtonMap, GamepadButton},
+    output::OutputConfig,
+    pad_state::DsButton,
+    stick::{DeadzoneShape, ResponseCurve},
+    touch::TouchHotspot,
+    turbo::Turbo,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct AppConfig {
     pub target_ip: String,
+    /// Where packets go; UDP to `target_ip` unless overridden.
+    pub output: OutputConfig,
+    pub gamepad: GamepadSelector,
     pub invert_lx: bool,
     pub invert_ly: bool,
     pub invert_rx: bool,
     pub invert_ry: bool,
     pub deadzone_lstick: f32,
     pub deadzone_rstick: f32,
+    pub outer_deadzone_lstick: f32,
+    pub outer_deadzone_rstick: f32,
+    pub deadzone_shape_lstick: DeadzoneShape,
+    pub deadzone_shape_rstick: DeadzoneShape,
+    pub sensitivity_lstick: f32,
+    pub sensitivity_rstick: f32,
+    pub curve_lstick: ResponseCurve,
+    pub curve_rstick: ResponseCurve,
+    /// Trigger axis value that presses the gamepad's LeftTrigger2/RightTrigger2;
+    /// `button_map` decides which 3DS buttons those drive.
+    pub trigger_press_threshold: f32,
+    pub trigger_release_threshold: f32,
+    /// When set, one stick presses the D-pad instead of moving its analog output.
+    pub stick_to_dpad: Option<StickToDpad>,
+    /// D-pad presses become full circle-pad deflections instead.
+    pub dpad_to_circle_pad: bool,
+    /// Gamepad buttons can also be mapped to these by name, to touch them.
+    pub touch_hotspots: Vec<TouchHotspot>,
+    pub button_map: ButtonMap,
+    /// 3DS buttons that autofire while held.
+    pub turbo_buttons: BTreeMap<DsButton, Turbo>,
+    /// Gamepad buttons that, held together, switch all turbo buttons on or off.
+    /// Empty means no hotkey.
+    pub turbo_toggle_chord: Vec<GamepadButton>,
+    /// Drive the 3DS from the keyboard while the GUI window has focus.
+    pub keyboard_input: bool,
+    pub key_map: KeyMap,
+    /// Recorded macros (stored in the `macros` directory) and the chords that replay them.
+    pub macros: Vec<MacroBinding>,
+    pub send_rate_hz: u32,
 }
 
 // Default values for the config
@@ -18,22 +65,49 @@ impl Default for AppConfig {
     fn default() -> Self {
         Self {
             target_ip: "0.0.0.0".into(),
+            output: OutputConfig::Udp,
+            gamepad: GamepadSelector::First,
             invert_lx: false,
             invert_ly: false,
             invert_rx: false,
             invert_ry: false,
             deadzone_lstick: 0.10, // 10%
             deadzone_rstick: 0.10, // 10%
+            outer_deadzone_lstick: 0.0,
+            outer_deadzone_rstick: 0.0,
+            deadzone_shape_lstick: DeadzoneShape::Axial,
+            deadzone_shape_rstick: DeadzoneShape::Axial,
+            sensitivity_lstick: 1.0,
+            sensitivity_rstick: 1.0,
+            curve_lstick: ResponseCurve::Linear,
+            curve_rstick: ResponseCurve::Linear,
+            trigger_press_threshold: 0.5,
+            trigger_release_threshold: 0.4,
+            stick_to_dpad: None,
+            dpad_to_circle_pad: false,
+            touch_hotspots: Vec::new(),
+            button_map: ButtonMap::default(),
+            turbo_buttons: BTreeMap::new(),
+            turbo_toggle_chord: Vec::new(),
+            keyboard_input: true,
+            key_map: KeyMap::default(),
+            macros: Vec::new(),
+            send_rate_hz: 60,
         }
     }
 }
 
-fn config_path() -> io::Result<PathBuf> {
+/// Directory holding `config.toml` and the files stored next to it.
+pub fn config_dir() -> io::Result<PathBuf> {
     ProjectDirs::from("com", "Rust3DSInputRedirector", "Rust3DSInputRedirector")
-        .map(|d| d.config_dir().join("config.toml"))
+        .map(|d| d.config_dir().to_path_buf())
         .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not determine config dir"))
 }
 
+fn config_path() -> io::Result<PathBuf> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
 impl AppConfig {
     pub fn load() -> io::Result<Self> {
         let path = config_path()?;
@@ -48,13 +122,12 @@ impl AppConfig {
 
     pub fn save(&self) -> io::Result<()> {
         let path = config_path()?;
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent)?;
-            }
+        if let Some(parent) = path.parent()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)?;
         }
-        let toml =
-            toml::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let toml = toml::to_string_pretty(self).map_err(io::Error::other)?;
         fs::write(path, toml)
     }
 }
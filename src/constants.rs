@@ -1,5 +1,7 @@
 // Network
 pub const TARGET_PORT: u16 = 4950;
+/// How many times the neutral packet is sent when releasing, in case one gets lost.
+pub const RELEASE_REPEAT: usize = 3;
 
 // Stick bounds & offsets
 pub const CPAD_BOUND: f32 = 0x5D0 as f32;
@@ -8,6 +10,12 @@ pub const ROT_CPP_BOUND: f32 = std::f32::consts::FRAC_1_SQRT_2 * CPP_BOUND;
 pub const CPAD_CENTER_OFFSET_INT: i32 = 0x800;
 pub const CPP_CENTER_OFFSET_INT: i32 = 0x80;
 
+// Touchscreen
+pub const TOUCH_SCREEN_WIDTH: u16 = 320;
+pub const TOUCH_SCREEN_HEIGHT: u16 = 240;
+pub const TOUCH_NONE: u32 = 0x0200_0000;
+pub const TOUCH_PRESSED_FLAG: u32 = 1 << 24;
+
 // Tiny “nudge” values
 pub const MINIMAL_NUDGE_LSTICK: f32 = 0.001;
 pub const MINIMAL_NUDGE_RSTICK: f32 = 0.008;
@@ -33,3 +41,10 @@ pub mod ir_bits {
     pub const ZR: u8 = 1;
     pub const ZL: u8 = 2;
 }
+
+// Interface button bits (HOME / POWER)
+pub mod iface_bits {
+    pub const HOME: u8 = 0;
+    pub const POWER: u8 = 1;
+    pub const POWER_LONG: u8 = 2;
+}
@@ -0,0 +1,111 @@
+// dpad.rs
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_4;
+
+use crate::{
+    constants::{MINIMAL_NUDGE_LSTICK, MINIMAL_NUDGE_RSTICK},
+    pad_state::{DsButton, PadState},
+};
+
+/// Which stick an emulation option reads from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Stick {
+    #[default]
+    Left,
+    Right,
+}
+
+/// How many directions a stick resolves to on the D-pad.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DpadWays {
+    /// Only the dominant direction; never two D-pad buttons at once.
+    #[default]
+    Four,
+    /// Diagonals press two neighbouring D-pad buttons.
+    Eight,
+}
+
+/// Turns a stick into D-pad presses. The stick itself is sent centered.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct StickToDpad {
+    pub stick: Stick,
+    /// Deflection, as a fraction of full, from which a direction is pressed.
+    pub threshold: f32,
+    pub ways: DpadWays,
+}
+
+impl Default for StickToDpad {
+    fn default() -> Self {
+        Self {
+            stick: Stick::Left,
+            threshold: 0.5,
+            ways: DpadWays::Four,
+        }
+    }
+}
+
+impl StickToDpad {
+    /// Presses the D-pad directions the processed stick points to, then centers the stick.
+    pub fn apply(&self, st: &mut PadState) {
+        let (x, y) = match self.stick {
+            Stick::Left => (st.lx, st.ly),
+            Stick::Right => (st.rx, st.ry),
+        };
+        if x.hypot(y) >= self.threshold.max(f32::EPSILON) {
+            let (right, left, up, down) = match self.ways {
+                DpadWays::Four if x.abs() >= y.abs() => (x > 0.0, x < 0.0, false, false),
+                DpadWays::Four => (false, false, y > 0.0, y < 0.0),
+                DpadWays::Eight => {
+                    // 45° sectors counter-clockwise from right: 0 = right, 2 = up, 4 = left, 6 = down
+                    let sector = (y.atan2(x) / FRAC_PI_4).round() as i32;
+                    let sector = sector.rem_euclid(8);
+                    (
+                        matches!(sector, 7 | 0 | 1),
+                        matches!(sector, 3..=5),
+                        matches!(sector, 1..=3),
+                        matches!(sector, 5..=7),
+                    )
+                }
+            };
+            for (button, pressed) in [
+                (DsButton::DRight, right),
+                (DsButton::DLeft, left),
+                (DsButton::DUp, up),
+                (DsButton::DDown, down),
+            ] {
+                if pressed {
+                    st.set_button(button, true);
+                }
+            }
+        }
+
+        match self.stick {
+            Stick::Left => (st.lx, st.ly) = (MINIMAL_NUDGE_LSTICK, MINIMAL_NUDGE_LSTICK),
+            Stick::Right => (st.rx, st.ry) = (MINIMAL_NUDGE_RSTICK, MINIMAL_NUDGE_RSTICK),
+        }
+    }
+}
+
+/// Replaces D-pad presses with a full circle-pad deflection in the same direction.
+/// The circle pad keeps its stick value while no D-pad direction is held.
+pub fn dpad_to_circle_pad(st: &mut PadState) {
+    let axis = |st: &PadState, pos: DsButton, neg: DsButton| {
+        st.is_pressed(pos) as i8 as f32 - st.is_pressed(neg) as i8 as f32
+    };
+    let x = axis(st, DsButton::DRight, DsButton::DLeft);
+    let y = axis(st, DsButton::DUp, DsButton::DDown);
+    for button in [
+        DsButton::DUp,
+        DsButton::DDown,
+        DsButton::DLeft,
+        DsButton::DRight,
+    ] {
+        st.set_button(button, false);
+    }
+
+    let magnitude = x.hypot(y);
+    if magnitude > 0.0 {
+        (st.lx, st.ly) = (x / magnitude, y / magnitude);
+    }
+}
@@ -0,0 +1,59 @@
+// error.rs
+use std::{fmt, io, path::PathBuf};
+
+/// Reasons Input-Redirection can fail to start.
+#[derive(Debug)]
+pub enum StartError {
+    /// The gamepad backend could not be initialised.
+    /// Boxed because `gilrs::Error` can carry a whole `Gilrs` instance.
+    Gilrs(Box<gilrs::Error>),
+    /// The target host name or IP did not resolve.
+    Resolve { target: String, source: io::Error },
+    /// The local UDP socket could not be bound.
+    Bind(io::Error),
+    /// The output file could not be created.
+    Output(PathBuf, io::Error),
+}
+
+impl StartError {
+    /// Process exit code used by the CLI for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            StartError::Gilrs(_) => 2,
+            StartError::Resolve { .. } => 3,
+            StartError::Bind(_) => 4,
+            StartError::Output(..) => 5,
+        }
+    }
+}
+
+impl From<gilrs::Error> for StartError {
+    fn from(e: gilrs::Error) -> Self {
+        StartError::Gilrs(Box::new(e))
+    }
+}
+
+impl fmt::Display for StartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartError::Gilrs(e) => write!(f, "Failed to initialize Gilrs: {e}"),
+            StartError::Resolve { target, source } => {
+                write!(f, "Invalid target '{target}': {source}")
+            }
+            StartError::Bind(e) => write!(f, "Failed to bind UDP socket: {e}"),
+            StartError::Output(path, e) => {
+                write!(f, "Failed to create output file {}: {e}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for StartError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StartError::Gilrs(e) => Some(e.as_ref()),
+            StartError::Resolve { source, .. } => Some(source),
+            StartError::Bind(e) | StartError::Output(_, e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+// gamepad.rs
+use gilrs::{Axis, EventType, GamepadId, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use crate::{
+    input::{InputAxis, InputEvent, InputSource},
+    mapping::GamepadButton,
+};
+
+/// Which connected gamepad Input-Redirection should read from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub enum GamepadSelector {
+    /// Whichever gamepad gilrs reports first.
+    #[default]
+    First,
+    /// Position in the list printed by `--list-gamepads`.
+    Index(usize),
+    /// Case-insensitive substring of the gamepad name.
+    Name(String),
+    /// Gamepad UUID, with or without dashes.
+    Uuid(String),
+}
+
+/// A connected gamepad, as shown to the user when picking one.
+#[derive(Debug, Clone)]
+pub struct GamepadInfo {
+    pub id: GamepadId,
+    pub index: usize,
+    pub name: String,
+    pub uuid: String,
+}
+
+/// Lists the connected gamepads in gilrs' enumeration order.
+pub fn list_gamepads(gilrs: &Gilrs) -> Vec<GamepadInfo> {
+    gilrs
+        .gamepads()
+        .filter(|(_, gp)| gp.is_connected())
+        .enumerate()
+        .map(|(index, (id, gp))| GamepadInfo {
+            id,
+            index,
+            name: gp.name().to_string(),
+            uuid: format_uuid(gp.uuid()),
+        })
+        .collect()
+}
+
+impl GamepadSelector {
+    pub fn matches(&self, info: &GamepadInfo) -> bool {
+        match self {
+            GamepadSelector::First => true,
+            GamepadSelector::Index(i) => info.index == *i,
+            GamepadSelector::Name(name) => info
+                .name
+                .to_lowercase()
+                .contains(&name.trim().to_lowercase()),
+            GamepadSelector::Uuid(uuid) => normalize_uuid(uuid) == normalize_uuid(&info.uuid),
+        }
+    }
+
+    /// Returns the first connected gamepad this selector matches.
+    pub fn find(&self, gilrs: &Gilrs) -> Option<GamepadInfo> {
+        list_gamepads(gilrs)
+            .into_iter()
+            .find(|info| self.matches(info))
+    }
+}
+
+impl fmt::Display for GamepadSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GamepadSelector::First => write!(f, "first available gamepad"),
+            GamepadSelector::Index(i) => write!(f, "gamepad #{i}"),
+            GamepadSelector::Name(name) => write!(f, "gamepad named '{name}'"),
+            GamepadSelector::Uuid(uuid) => write!(f, "gamepad with UUID {uuid}"),
+        }
+    }
+}
+
+fn format_uuid(bytes: [u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn normalize_uuid(uuid: &str) -> String {
+    uuid.chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Input source reading the gamepad picked by a `GamepadSelector` through gilrs.
+/// Follows that gamepad across disconnects and reconnects; other gamepads are ignored.
+pub struct GilrsSource {
+    gilrs: Gilrs,
+    selector: GamepadSelector,
+    /// The gamepad currently followed, if one matching the selector is connected.
+    active: Option<GamepadInfo>,
+    /// Events generated by the source itself, delivered before gilrs' own.
+    pending: VecDeque<InputEvent>,
+}
+
+impl GilrsSource {
+    pub fn new(gilrs: Gilrs, selector: GamepadSelector) -> Self {
+        let mut source = Self {
+            gilrs,
+            selector,
+            active: None,
+            pending: VecDeque::new(),
+        };
+        source.find();
+        source
+    }
+
+    /// Starts following the selected gamepad if connected, otherwise reports waiting.
+    fn find(&mut self) {
+        match self.selector.find(&self.gilrs) {
+            Some(info) => self.bind(info),
+            None => self
+                .pending
+                .push_back(InputEvent::WaitingForGamepad(self.selector.clone())),
+        }
+    }
+
+    fn bind(&mut self, info: GamepadInfo) {
+        self.pending
+            .push_back(InputEvent::Connected(info.name.clone()));
+        self.active = Some(info);
+    }
+
+    /// Turns a gilrs event into an input event, handling connection changes.
+    fn convert(&mut self, evt: gilrs::Event) -> Option<InputEvent> {
+        match evt.event {
+            // Connection changes matter for any gamepad, input only for the active one
+            EventType::Connected => {
+                if self.active.is_none()
+                    && let Some(info) = list_gamepads(&self.gilrs)
+                        .into_iter()
+                        .find(|info| info.id == evt.id && self.selector.matches(info))
+                {
+                    self.bind(info);
+                }
+                self.pending.pop_front()
+            }
+            EventType::Disconnected => {
+                let info = self.active.take_if(|info| info.id == evt.id)?;
+                // gilrs already counts the pad as gone, so this switches to another
+                // connected match, or reports waiting if there is none
+                self.find();
+                Some(InputEvent::Disconnected(info.name))
+            }
+            _ if self.active.as_ref().map(|info| info.id) != Some(evt.id) => None,
+            EventType::AxisChanged(axis, value, _) => {
+                let axis = match axis {
+                    Axis::LeftStickX => InputAxis::LeftStickX,
+                    Axis::LeftStickY => InputAxis::LeftStickY,
+                    Axis::RightStickX => InputAxis::RightStickX,
+                    Axis::RightStickY => InputAxis::RightStickY,
+                    // Some backends report ZL/ZR-style triggers only as these axes
+                    Axis::LeftZ => InputAxis::LeftTrigger,
+                    Axis::RightZ => InputAxis::RightTrigger,
+                    _ => return None,
+                };
+                let value = match axis {
+                    // gilrs reports every axis in -1..=1, so a released trigger reads -1
+                    InputAxis::LeftTrigger | InputAxis::RightTrigger => (value + 1.0) / 2.0,
+                    _ => value,
+                };
+                Some(InputEvent::Axis(axis, value))
+            }
+            EventType::ButtonPressed(b, _) => {
+                GamepadButton::from_gilrs(b).map(|b| InputEvent::Button(b, true))
+            }
+            EventType::ButtonReleased(b, _) => {
+                GamepadButton::from_gilrs(b).map(|b| InputEvent::Button(b, false))
+            }
+            _ => None, // Other event types are ignored
+        }
+    }
+}
+
+impl InputSource for GilrsSource {
+    fn poll_event(&mut self) -> Option<InputEvent> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+        while let Some(evt) = self.gilrs.next_event() {
+            if let Some(event) = self.convert(evt) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    fn wait_event(&mut self, timeout: Duration) -> Option<InputEvent> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+        let evt = self.gilrs.next_event_blocking(Some(timeout))?;
+        self.convert(evt)
+    }
+
+    /// Drops the followed gamepad and looks for one matching the new selector.
+    fn select_gamepad(&mut self, selector: &GamepadSelector) {
+        if *selector == self.selector {
+            return;
+        }
+        self.selector = selector.clone();
+        if let Some(info) = self.active.take() {
+            self.pending.push_back(InputEvent::Disconnected(info.name));
+        }
+        self.find();
+    }
+}
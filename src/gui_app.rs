@@ -2,51 +2,143 @@
 use eframe::{App, egui};
 use gilrs::Gilrs;
 use std::{
+    collections::BTreeSet,
+    path::PathBuf,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
     },
     thread::{self, JoinHandle},
+    time::Duration,
 };
 
 use crate::{
-    config::AppConfig,
-    poller_worker::{PollerConfig, PollerWorker},
+    config::{AppConfig, config_dir},
+    constants::{TOUCH_SCREEN_HEIGHT, TOUCH_SCREEN_WIDTH},
+    dpad::{DpadWays, Stick, StickToDpad},
+    error::StartError,
+    gamepad::{GamepadInfo, GamepadSelector, GilrsSource, list_gamepads},
+    input::{ChannelSource, InputEvent, InputSource},
+    macros::MacroBinding,
+    mapping::GamepadButton,
+    output::OutputConfig,
+    pad_state::{DsButton, PadState},
+    poller_worker::{PollerConfig, PollerWorker, WorkerCommand, WorkerStatus},
+    session,
+    stick::{DeadzoneShape, ResponseCurve},
+    turbo::Turbo,
 };
 
+/// A replay thread; it returns the last send error, if packets failed to go out along the way.
+type ReplayHandle = JoinHandle<anyhow::Result<Option<String>>>;
+
+/// Packet rates offered in the GUI; other values can still be set in config.toml.
+const SEND_RATES_HZ: [u32; 3] = [60, 120, 250];
+
+/// Grid drawn over the touchscreen canvas, in 40-pixel cells.
+const TOUCH_GRID_COLUMNS: u16 = 8;
+const TOUCH_GRID_ROWS: u16 = 6;
+
 pub struct GuiApp {
-    // GUI input fields
-    target_ip_str: String,
-    invert_lx: bool,
-    invert_ly: bool,
-    invert_rx: bool,
-    invert_ry: bool,
-    deadzone_lstick_f32: f32,
-    deadzone_rstick_f32: f32,
+    // GUI input fields, edited in place; fields without a widget are kept as loaded
+    config: AppConfig,
+    // Target IP as typed; copied into `config` when the field loses focus so a
+    // running worker doesn't try to resolve every half-typed address
+    target_ip_edit: String,
+
+    // Gamepads offered in the selection dropdown
+    gamepads: Vec<GamepadInfo>,
+
+    // Name the next macro recording is saved under
+    macro_name_edit: String,
+    // Feeds the worker's keyboard and touchscreen input source while it runs
+    input_tx: Option<mpsc::Sender<InputEvent>>,
+    // Keys forwarded as held, released when the window loses focus
+    keys_held: BTreeSet<String>,
+    // Touch point last forwarded from the touchscreen canvas
+    touch: Option<(u16, u16)>,
+    // Whether the canvas was drawn this frame; if not, nothing can be touching it
+    touch_canvas_drawn: bool,
+    // Whether the worker is recording a macro
+    recording: bool,
+
+    // Session file to record to or replay from
+    session_path_edit: String,
+    // Whether the worker is writing a session file
+    session_recording: bool,
+    replay_speed: f32,
+    replay_loop: bool,
+    // Stop flag and thread of a replay in progress
+    replay: Option<(Arc<AtomicBool>, ReplayHandle)>,
 
     // PollerWorker management
     poller_running_signal: Option<Arc<AtomicBool>>,
     poller_worker_handle: Option<JoinHandle<()>>,
+    poller_status_rx: Option<Receiver<WorkerStatus>>,
+    poller_command_tx: Option<mpsc::Sender<WorkerCommand>>,
+    // Last configuration pushed to the running worker
+    applied_config: Option<PollerConfig>,
 
     // Status message
     status_message: String,
+    // Gamepad connection as last reported by the worker
+    gamepad_status: String,
+    // Packets/sec and pad state from the worker's latest statistics report
+    stats: Option<(u32, PadState)>,
 }
 
 impl GuiApp {
     pub fn new() -> Self {
-        let app_config = AppConfig::load().unwrap_or_default();
-
-        Self {
-            target_ip_str: app_config.target_ip,
-            invert_lx: app_config.invert_lx,
-            invert_ly: app_config.invert_ly,
-            invert_rx: app_config.invert_rx,
-            invert_ry: app_config.invert_ry,
-            deadzone_lstick_f32: app_config.deadzone_lstick,
-            deadzone_rstick_f32: app_config.deadzone_rstick,
+        let config = AppConfig::load().unwrap_or_default();
+        let mut app = Self {
+            target_ip_edit: config.target_ip.clone(),
+            config,
+            gamepads: Vec::new(),
+            macro_name_edit: String::new(),
+            input_tx: None,
+            keys_held: BTreeSet::new(),
+            touch: None,
+            touch_canvas_drawn: false,
+            recording: false,
+            session_path_edit: config_dir()
+                .map(|dir| dir.join("session.irs").display().to_string())
+                .unwrap_or_default(),
+            session_recording: false,
+            replay_speed: 1.0,
+            replay_loop: false,
+            replay: None,
             poller_running_signal: None,
             poller_worker_handle: None,
+            poller_status_rx: None,
+            poller_command_tx: None,
+            applied_config: None,
             status_message: "Ready. Configure and start Input-Redirection.".to_string(),
+            gamepad_status: String::new(),
+            stats: None,
+        };
+        app.refresh_gamepads();
+        app
+    }
+
+    /// Re-enumerates the connected gamepads for the selection dropdown.
+    fn refresh_gamepads(&mut self) {
+        match Gilrs::new() {
+            Ok(gilrs) => self.gamepads = list_gamepads(&gilrs),
+            Err(e) => self.status_message = StartError::from(e).to_string(),
+        }
+    }
+
+    /// Text shown in the dropdown for the current selection.
+    fn selected_gamepad_text(&self) -> String {
+        match &self.config.gamepad {
+            GamepadSelector::First => "First available".to_string(),
+            selector => self
+                .gamepads
+                .iter()
+                .find(|info| selector.matches(info))
+                .map(|info| format!("#{} {}", info.index, info.name))
+                .unwrap_or_else(|| format!("{selector} (not connected)")),
         }
     }
 
@@ -55,74 +147,363 @@ impl GuiApp {
             self.status_message = "Input-Redirection is already running or finishing.".to_string();
             return;
         }
+        if self.replay.is_some() {
+            self.status_message = "Stop the replay before starting Input-Redirection.".to_string();
+            return;
+        }
 
         // --- SAVE CURRENT GUI STATE TO CONFIG ---
-        let new_config = AppConfig {
-            target_ip: self.target_ip_str.clone(),
-            invert_lx: self.invert_lx,
-            invert_ly: self.invert_ly,
-            invert_rx: self.invert_rx,
-            invert_ry: self.invert_ry,
-            deadzone_lstick: self.deadzone_lstick_f32,
-            deadzone_rstick: self.deadzone_rstick_f32,
-        };
-
-        if let Err(e) = new_config.save() {
+        self.commit_target_ip();
+        if let Err(e) = self.config.save() {
             self.status_message = format!("Failed to save config: {:?}", e);
             return;
         }
 
-        // 1. Initialize Gilrs
-        let gilrs_instance = match Gilrs::new() {
-            Ok(g) => g,
+        // 1. Set up the input sources: the gamepad through gilrs, and the keyboard and
+        // touchscreen canvas, which can still drive the 3DS when gilrs is unavailable
+        let mut sources: Vec<Box<dyn InputSource>> = Vec::new();
+        match Gilrs::new() {
+            Ok(gilrs) => sources.push(Box::new(GilrsSource::new(
+                gilrs,
+                self.config.gamepad.clone(),
+            ))),
+            Err(e) if self.config.keyboard_input => {
+                self.gamepad_status = format!("{}; keyboard only", StartError::from(e));
+            }
             Err(e) => {
-                self.status_message = format!("Failed to initialize Gilrs: {:?}", e);
+                self.status_message = StartError::from(e).to_string();
                 return;
             }
-        };
+        }
+        let (input_tx, gui_source) = ChannelSource::new();
+        sources.push(Box::new(gui_source));
 
-        // 2. Find an active gamepad
-        let (active_id, gamepad_name) = match gilrs_instance.gamepads().next() {
-            Some((id, gamepad)) => (id, gamepad.name().to_string()),
-            None => {
-                self.status_message = "No gamepad connected. Please connect a gamepad.".to_string();
+        // 2. Create PollerConfig from current GUI state
+        let poller_config = PollerConfig::from(&self.config);
+
+        // 3. Prepare running signal and status channel, then spawn worker.
+        // The worker waits for the selected gamepad and reports when it connects.
+        let running_signal = Arc::new(AtomicBool::new(true));
+        let (status_tx, status_rx) = mpsc::channel();
+
+        let mut poller_worker = match PollerWorker::new(
+            sources,
+            poller_config.clone(),
+            running_signal.clone(),
+            status_tx,
+        ) {
+            Ok(worker) => worker,
+            Err(e) => {
+                self.status_message = e.to_string();
                 return;
             }
         };
+        self.poller_running_signal = Some(running_signal);
+        self.poller_status_rx = Some(status_rx);
+        self.poller_command_tx = Some(poller_worker.commands());
+        self.input_tx = Some(input_tx);
+        self.applied_config = Some(poller_config);
 
-        // 3. Create PollerConfig from current GUI state
-        let poller_config = PollerConfig {
-            target_ip: self.target_ip_str.clone(),
-            deadzone_lstick: self.deadzone_lstick_f32,
-            deadzone_rstick: self.deadzone_rstick_f32,
-            invert_lx: self.invert_lx,
-            invert_ly: self.invert_ly,
-            invert_rx: self.invert_rx,
-            invert_ry: self.invert_ry,
+        let handle = thread::spawn(move || {
+            poller_worker.run(); // This function now prints to console from the worker
+        });
+
+        self.poller_worker_handle = Some(handle);
+        self.status_message = "Config saved; Input-Redirection started.".to_string();
+    }
+
+    /// Takes the typed target IP into the config.
+    fn commit_target_ip(&mut self) {
+        self.config.target_ip = self.target_ip_edit.trim().to_string();
+    }
+
+    /// Sends the current settings to the running worker if they changed since last time.
+    fn push_live_config(&mut self) {
+        let Some(tx) = &self.poller_command_tx else {
+            return;
         };
+        let cfg = PollerConfig::from(&self.config);
+        if self.applied_config.as_ref() != Some(&cfg) {
+            let _ = tx.send(WorkerCommand::UpdateConfig(Box::new(cfg.clone())));
+            self.applied_config = Some(cfg);
+        }
+    }
 
-        // 4. Prepare running signal and spawn worker
-        let running_signal = Arc::new(AtomicBool::new(true));
-        self.poller_running_signal = Some(running_signal.clone());
+    /// Forwards key presses to the running worker while the window has focus and no
+    /// text field is being edited. Held keys are released as soon as that stops.
+    fn forward_keys(&mut self, ctx: &egui::Context) {
+        let Some(tx) = &self.input_tx else {
+            return;
+        };
+        let active =
+            self.config.keyboard_input && ctx.input(|i| i.focused) && !ctx.wants_keyboard_input();
+        if !active {
+            for name in std::mem::take(&mut self.keys_held) {
+                let _ = tx.send(InputEvent::Key(name, false));
+            }
+            return;
+        }
 
-        let mut poller_worker =
-            PollerWorker::new(gilrs_instance, active_id, poller_config, running_signal);
+        let keys: Vec<_> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed,
+                        repeat: false,
+                        ..
+                    } => Some((key.name().to_string(), *pressed)),
+                    _ => None,
+                })
+                .collect()
+        });
+        for (name, pressed) in keys {
+            if pressed {
+                self.keys_held.insert(name.clone());
+            } else {
+                self.keys_held.remove(&name);
+            }
+            let _ = tx.send(InputEvent::Key(name, pressed));
+        }
+    }
 
-        let handle = thread::spawn(move || {
-            poller_worker.run(); // This function now prints to console from the worker
+    /// The bottom screen, scaled to the panel width: pressing or dragging on it holds
+    /// the touchscreen at that point until the mouse button is released.
+    /// Touch hotspots from the config are marked on it.
+    fn touch_ui(&mut self, ui: &mut egui::Ui) {
+        self.touch_canvas_drawn = true;
+        let (width, height) = (TOUCH_SCREEN_WIDTH as f32, TOUCH_SCREEN_HEIGHT as f32);
+        let scale = ui.available_width() / width;
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(width, height) * scale, egui::Sense::drag());
+        let rect = response.rect;
+        let to_screen = |x: f32, y: f32| rect.min + egui::vec2(x, y) * scale;
+
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+        let grid = egui::Stroke::new(1.0, visuals.weak_text_color().gamma_multiply(0.3));
+        for i in 1..TOUCH_GRID_COLUMNS {
+            let x = width * i as f32 / TOUCH_GRID_COLUMNS as f32;
+            painter.line_segment([to_screen(x, 0.0), to_screen(x, height)], grid);
+        }
+        for i in 1..TOUCH_GRID_ROWS {
+            let y = height * i as f32 / TOUCH_GRID_ROWS as f32;
+            painter.line_segment([to_screen(0.0, y), to_screen(width, y)], grid);
+        }
+        painter.rect_stroke(
+            rect,
+            0.0,
+            visuals.widgets.noninteractive.bg_stroke,
+            egui::StrokeKind::Inside,
+        );
+
+        // Pointer position in screen pixels, clamped so dragging off the edge stays on it
+        let to_touch = |pos: egui::Pos2| {
+            let p = (pos - rect.min) / scale;
+            (
+                p.x.clamp(0.0, width - 1.0) as u16,
+                p.y.clamp(0.0, height - 1.0) as u16,
+            )
+        };
+        let touch = if self.input_tx.is_some() && response.is_pointer_button_down_on() {
+            response.interact_pointer_pos().map(to_touch)
+        } else {
+            None
+        };
+        for hotspot in &self.config.touch_hotspots {
+            let center = to_screen(hotspot.x as f32, hotspot.y as f32);
+            painter.circle_stroke(center, 5.0, egui::Stroke::new(1.0, visuals.warn_fg_color));
+            painter.text(
+                center + egui::vec2(0.0, 7.0),
+                egui::Align2::CENTER_TOP,
+                &hotspot.name,
+                egui::FontId::proportional(10.0),
+                visuals.warn_fg_color,
+            );
+        }
+        if let Some(pos) = response.hover_pos() {
+            let (x, y) = to_touch(pos);
+            let cursor = to_screen(x as f32, y as f32);
+            let stroke = egui::Stroke::new(1.0, visuals.text_color());
+            painter.line_segment(
+                [cursor - egui::vec2(6.0, 0.0), cursor + egui::vec2(6.0, 0.0)],
+                stroke,
+            );
+            painter.line_segment(
+                [cursor - egui::vec2(0.0, 6.0), cursor + egui::vec2(0.0, 6.0)],
+                stroke,
+            );
+        }
+        if let Some((x, y)) = touch {
+            painter.circle_filled(
+                to_screen(x as f32, y as f32),
+                4.0,
+                visuals.selection.bg_fill,
+            );
+        }
+
+        if touch != self.touch
+            && let Some(tx) = &self.input_tx
+        {
+            let _ = tx.send(InputEvent::Touch(touch));
+            self.touch = touch;
+        }
+        match touch {
+            Some((x, y)) => ui.label(format!("Touching ({x}, {y})")),
+            None if self.input_tx.is_some() => ui.label("Click or drag to touch"),
+            None => ui.label("Start Input-Redirection to use the touchscreen"),
+        };
+    }
+
+    /// Lifts a touch whose canvas stopped being drawn mid-press (e.g. its section was
+    /// collapsed), since `touch_ui` only releases it while the canvas is drawn.
+    fn release_hidden_touch(&mut self) {
+        if self.touch.is_some()
+            && !self.touch_canvas_drawn
+            && let Some(tx) = &self.input_tx
+        {
+            let _ = tx.send(InputEvent::Touch(None));
+            self.touch = None;
+        }
+    }
+
+    /// Recording controls and the chord, loop and cancel settings of each bound macro.
+    /// Recording needs a running worker, since it records what the worker sends.
+    fn macros_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.add_enabled(
+                !self.recording,
+                egui::TextEdit::singleline(&mut self.macro_name_edit).desired_width(120.0),
+            );
+            if let Some(tx) = &self.poller_command_tx {
+                if self.recording {
+                    if ui.button("Stop recording").clicked() {
+                        let _ = tx.send(WorkerCommand::StopRecording);
+                    }
+                } else if ui.button("Record").clicked() {
+                    let name = self.macro_name_edit.trim().to_string();
+                    let _ = tx.send(WorkerCommand::StartRecording(name));
+                }
+            } else {
+                ui.add_enabled(false, egui::Button::new("Record"))
+                    .on_disabled_hover_text("Start Input-Redirection to record");
+            }
         });
 
-        self.poller_worker_handle = Some(handle);
-        self.status_message = format!(
-            "Config saved; Input-Redirection started with gamepad: '{}'",
-            gamepad_name
+        let mut removed = None;
+        for (i, binding) in self.config.macros.iter_mut().enumerate() {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(&binding.name);
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(i);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Chord:");
+                let chord_text = if binding.chord.is_empty() {
+                    "none".to_string()
+                } else {
+                    format!("{:?}", binding.chord)
+                };
+                egui::ComboBox::from_id_salt(("macro_chord", i))
+                    .selected_text(chord_text)
+                    .show_ui(ui, |ui| {
+                        for button in GamepadButton::ALL {
+                            let mut in_chord = binding.chord.contains(&button);
+                            if ui.checkbox(&mut in_chord, format!("{button:?}")).changed() {
+                                if in_chord {
+                                    binding.chord.push(button);
+                                } else {
+                                    binding.chord.retain(|b| *b != button);
+                                }
+                            }
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut binding.looped, "Loop");
+                ui.checkbox(&mut binding.cancel_on_input, "Cancel on input");
+            });
+        }
+        if let Some(i) = removed {
+            // The recording itself stays in the macros directory
+            self.config.macros.remove(i);
+        }
+    }
+
+    /// Records what the running worker sends to a session file, or replays one
+    /// to the target while the worker is stopped.
+    fn session_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("File:");
+        ui.add_enabled(
+            !self.session_recording && self.replay.is_none(),
+            egui::TextEdit::singleline(&mut self.session_path_edit),
         );
+        let path = PathBuf::from(self.session_path_edit.trim());
+
+        ui.horizontal(|ui| match &self.poller_command_tx {
+            Some(tx) if self.session_recording => {
+                if ui.button("Stop recording").clicked() {
+                    let _ = tx.send(WorkerCommand::StopSession);
+                }
+            }
+            Some(tx) => {
+                if ui.button("Record").clicked() {
+                    let _ = tx.send(WorkerCommand::StartSession(path.clone()));
+                }
+            }
+            None => {
+                ui.add_enabled(false, egui::Button::new("Record"))
+                    .on_disabled_hover_text("Start Input-Redirection to record");
+            }
+        });
+
+        ui.separator();
+        ui.add(egui::Slider::new(&mut self.replay_speed, 0.25..=4.0).text("x speed"));
+        ui.checkbox(&mut self.replay_loop, "Loop");
+        let is_poller_active = self.poller_worker_handle.is_some();
+        ui.horizontal(|ui| {
+            if let Some((stop, _)) = &self.replay {
+                if ui.button("Stop replay").clicked() {
+                    stop.store(false, Ordering::SeqCst);
+                }
+            } else if ui
+                .add_enabled(!is_poller_active, egui::Button::new("Replay"))
+                .on_disabled_hover_text("Stop Input-Redirection to replay")
+                .clicked()
+            {
+                self.commit_target_ip();
+                let running = Arc::new(AtomicBool::new(true));
+                let flag = running.clone();
+                let target_ip = self.config.target_ip.clone();
+                let (speed, looped) = (self.replay_speed, self.replay_loop);
+                let handle = thread::spawn(move || {
+                    let mut send_error = None;
+                    session::replay_file(&path, &target_ip, speed, looped, &flag, |e| {
+                        send_error = Some(e.to_string())
+                    })?;
+                    Ok(send_error)
+                });
+                self.replay = Some((running, handle));
+                self.status_message = "Replaying session...".to_string();
+            }
+        });
     }
 
     fn stop_poller(&mut self) {
+        // Keep whatever was tweaked while running
+        self.commit_target_ip();
+        if let Err(e) = self.config.save() {
+            eprintln!("Failed to save config: {:?}", e);
+        }
+
         if let Some(signal) = &self.poller_running_signal {
             signal.store(false, Ordering::SeqCst);
-            self.status_message = "Stop signal sent to Input-Redirection. It will stop shortly.".to_string();
+            self.status_message =
+                "Stop signal sent to Input-Redirection. It will stop shortly.".to_string();
             // The update loop will handle joining the thread once it's finished.
         } else {
             self.status_message = "Input-Redirection is not currently running.".to_string();
@@ -132,12 +513,58 @@ impl GuiApp {
 
 impl App for GuiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Show the latest status update from the worker
+        if let Some(rx) = &self.poller_status_rx {
+            while let Ok(status) = rx.try_recv() {
+                match status {
+                    WorkerStatus::Stats {
+                        packets_per_sec,
+                        state,
+                    } => self.stats = Some((packets_per_sec, state)),
+                    WorkerStatus::WaitingForGamepad(_)
+                    | WorkerStatus::GamepadConnected(_)
+                    | WorkerStatus::GamepadDisconnected(_) => {
+                        self.gamepad_status = status.to_string()
+                    }
+                    WorkerStatus::RecordingStarted(_) => {
+                        self.recording = true;
+                        self.status_message = status.to_string();
+                    }
+                    WorkerStatus::SessionStarted(_) => {
+                        self.session_recording = true;
+                        self.status_message = status.to_string();
+                    }
+                    WorkerStatus::SessionSaved(_) | WorkerStatus::SessionFailed(_) => {
+                        self.session_recording = false;
+                        self.status_message = status.to_string();
+                    }
+                    WorkerStatus::MacroFailed(_) if self.recording => {
+                        // Most likely the save at the end of the recording failed
+                        self.recording = false;
+                        self.status_message = status.to_string();
+                    }
+                    WorkerStatus::MacroSaved(ref name) => {
+                        self.recording = false;
+                        // Offer the new recording for binding right away
+                        if !self.config.macros.iter().any(|m| &m.name == name) {
+                            self.config.macros.push(MacroBinding {
+                                name: name.clone(),
+                                ..Default::default()
+                            });
+                        }
+                        self.status_message = status.to_string();
+                    }
+                    _ => self.status_message = status.to_string(),
+                }
+            }
+        }
+
         // Check if the poller thread has finished and handle joining
         let mut poller_just_stopped = false;
-        if let Some(handle) = &self.poller_worker_handle {
-            if handle.is_finished() {
-                poller_just_stopped = true;
-            }
+        if let Some(handle) = &self.poller_worker_handle
+            && handle.is_finished()
+        {
+            poller_just_stopped = true;
         }
 
         if poller_just_stopped {
@@ -145,7 +572,8 @@ impl App for GuiApp {
                 match handle_to_join.join() {
                     Ok(_) => {
                         self.status_message =
-                            "Input-Redirection thread finished and joined successfully.".to_string();
+                            "Input-Redirection thread finished and joined successfully."
+                                .to_string();
                     }
                     Err(e) => {
                         self.status_message = format!("Input-Redirection thread panicked: {:?}", e);
@@ -154,44 +582,42 @@ impl App for GuiApp {
             }
             // Clear the signal as the poller is no longer active
             self.poller_running_signal = None;
+            self.poller_status_rx = None;
+            self.poller_command_tx = None;
+            self.applied_config = None;
+            self.gamepad_status.clear();
+            self.stats = None;
+            self.recording = false;
+            self.session_recording = false;
+            self.input_tx = None;
+            self.keys_held.clear();
+            self.touch = None;
         }
 
-        let is_poller_active = self.poller_worker_handle.is_some();
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("3DS Input-Redirection Configuration");
-            ui.add_space(10.0);
-
-            // --- Configuration Group ---
-            // Configuration fields are disabled if the poller is active
-            ui.group(|ui| {
-                ui.add_enabled_ui(!is_poller_active, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("Target IP:");
-                        ui.text_edit_singleline(&mut self.target_ip_str);
-                    });
-                    ui.add_space(5.0);
-
-                    ui.label("Left Stick Deadzone:");
-                    ui.add(
-                        egui::Slider::new(&mut self.deadzone_lstick_f32, 0.0..=0.99).step_by(0.01),
-                    );
-                    ui.label("Right Stick Deadzone:");
-                    ui.add(
-                        egui::Slider::new(&mut self.deadzone_rstick_f32, 0.0..=0.99).step_by(0.01),
-                    );
-                    ui.add_space(5.0);
+        if let Some((_, handle)) = &self.replay
+            && handle.is_finished()
+            && let Some((_, handle)) = self.replay.take()
+        {
+            self.status_message = match handle.join() {
+                Ok(Ok(None)) => "Replay finished.".to_string(),
+                Ok(Ok(Some(e))) => format!("Replay finished; some packets failed to send: {e}"),
+                Ok(Err(e)) => format!("Replay failed: {e}"),
+                Err(e) => format!("Replay thread panicked: {:?}", e),
+            };
+        }
 
-                    ui.label("Axis Inversions:");
-                    ui.checkbox(&mut self.invert_lx, "Invert Left Stick X");
-                    ui.checkbox(&mut self.invert_ly, "Invert Left Stick Y");
-                    ui.checkbox(&mut self.invert_rx, "Invert Right Stick X");
-                    ui.checkbox(&mut self.invert_ry, "Invert Right Stick Y");
-                });
-            });
+        self.forward_keys(ctx);
+        self.touch_canvas_drawn = false;
 
-            ui.separator();
+        let is_poller_active = self.poller_worker_handle.is_some();
+        if is_poller_active || self.replay.is_some() {
+            // Keep polling worker status even when there is no user input
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
 
+        // Controls and status stay pinned at the bottom; the settings scroll above them
+        egui::TopBottomPanel::bottom("controls").show(ctx, |ui| {
+            ui.add_space(5.0);
             // --- Control Buttons ---
             ui.horizontal(|ui| {
                 if is_poller_active {
@@ -210,12 +636,193 @@ impl App for GuiApp {
             // --- Status Display ---
             ui.label("Status:");
             ui.label(&self.status_message);
+            if is_poller_active {
+                ui.label(&self.gamepad_status);
+                if let Some((packets_per_sec, state)) = &self.stats {
+                    ui.label(format!("{packets_per_sec} packets/s"));
+                    ui.small(state.to_string());
+                }
+            }
+            ui.add_space(5.0);
         });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("3DS Input-Redirection Configuration");
+            ui.add_space(10.0);
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // --- Configuration Group ---
+                // Configuration fields stay editable while running; changes are pushed live
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Target IP:");
+                        let response = ui.text_edit_singleline(&mut self.target_ip_edit);
+                        if response.lost_focus() {
+                            self.commit_target_ip();
+                        }
+                    });
+                    ui.add_space(5.0);
+
+                    ui.horizontal(|ui| {
+                        ui.label("Output:");
+                        egui::ComboBox::from_id_salt("output_select")
+                            .selected_text(self.config.output.to_string())
+                            .width(180.0)
+                            .show_ui(ui, |ui| {
+                                for output in output_choices() {
+                                    let text = output.to_string();
+                                    ui.selectable_value(&mut self.config.output, output, text);
+                                }
+                            });
+                    })
+                    .response
+                    .on_hover_text("Other file paths can be set in config.toml (output)");
+                    ui.add_space(5.0);
+
+                    ui.horizontal(|ui| {
+                        ui.label("Gamepad:");
+                        egui::ComboBox::from_id_salt("gamepad_select")
+                            .selected_text(self.selected_gamepad_text())
+                            .width(180.0)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut self.config.gamepad,
+                                    GamepadSelector::First,
+                                    "First available",
+                                );
+                                for info in &self.gamepads {
+                                    // UUIDs identify the model, not the device, so
+                                    // identical pads are told apart by position instead
+                                    let identical = self
+                                        .gamepads
+                                        .iter()
+                                        .filter(|other| other.uuid == info.uuid)
+                                        .count();
+                                    let selector = if identical > 1 {
+                                        GamepadSelector::Index(info.index)
+                                    } else {
+                                        GamepadSelector::Uuid(info.uuid.clone())
+                                    };
+                                    ui.selectable_value(
+                                        &mut self.config.gamepad,
+                                        selector,
+                                        format!("#{} {}", info.index, info.name),
+                                    );
+                                }
+                            });
+                        if ui.button("Refresh").clicked() {
+                            self.refresh_gamepads();
+                        }
+                    });
+                    ui.add_space(5.0);
+
+                    ui.horizontal(|ui| {
+                        ui.label("Send rate:");
+                        egui::ComboBox::from_id_salt("send_rate")
+                            .selected_text(format!("{} Hz", self.config.send_rate_hz))
+                            .show_ui(ui, |ui| {
+                                for hz in SEND_RATES_HZ {
+                                    ui.selectable_value(
+                                        &mut self.config.send_rate_hz,
+                                        hz,
+                                        format!("{hz} Hz"),
+                                    );
+                                }
+                            });
+                    });
+                    ui.add_space(5.0);
+
+                    ui.checkbox(
+                        &mut self.config.keyboard_input,
+                        "Keyboard input while this window has focus",
+                    )
+                    .on_hover_text("Key bindings are set in config.toml (key_map)");
+                    ui.add_space(5.0);
+
+                    let cfg = &mut self.config;
+                    egui::CollapsingHeader::new("Left Stick")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            stick_settings_ui(
+                                ui,
+                                &mut cfg.deadzone_lstick,
+                                &mut cfg.outer_deadzone_lstick,
+                                &mut cfg.deadzone_shape_lstick,
+                                &mut cfg.invert_lx,
+                                &mut cfg.invert_ly,
+                            );
+                            response_ui(
+                                ui,
+                                "lstick_curve",
+                                &mut cfg.curve_lstick,
+                                &mut cfg.sensitivity_lstick,
+                            );
+                        });
+                    egui::CollapsingHeader::new("Right Stick")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            stick_settings_ui(
+                                ui,
+                                &mut cfg.deadzone_rstick,
+                                &mut cfg.outer_deadzone_rstick,
+                                &mut cfg.deadzone_shape_rstick,
+                                &mut cfg.invert_rx,
+                                &mut cfg.invert_ry,
+                            );
+                            response_ui(
+                                ui,
+                                "rstick_curve",
+                                &mut cfg.curve_rstick,
+                                &mut cfg.sensitivity_rstick,
+                            );
+                        });
+                    egui::CollapsingHeader::new("Analog Triggers")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.label("Press threshold:");
+                            ui.add(
+                                egui::Slider::new(&mut cfg.trigger_press_threshold, 0.05..=1.0)
+                                    .step_by(0.01),
+                            );
+                            ui.label("Release threshold:");
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut cfg.trigger_release_threshold,
+                                    0.0..=cfg.trigger_press_threshold,
+                                )
+                                .step_by(0.01),
+                            );
+                        });
+                    egui::CollapsingHeader::new("D-pad Emulation")
+                        .default_open(false)
+                        .show(ui, |ui| dpad_emulation_ui(ui, cfg));
+                    egui::CollapsingHeader::new("Turbo")
+                        .default_open(false)
+                        .show(ui, |ui| turbo_ui(ui, cfg));
+                    egui::CollapsingHeader::new("Touchscreen")
+                        .default_open(false)
+                        .show(ui, |ui| self.touch_ui(ui));
+                    egui::CollapsingHeader::new("Macros")
+                        .default_open(false)
+                        .show(ui, |ui| self.macros_ui(ui));
+                    egui::CollapsingHeader::new("Session Recording")
+                        .default_open(false)
+                        .show(ui, |ui| self.session_ui(ui));
+                });
+            });
+        });
+
+        self.release_hidden_touch();
+        self.push_live_config();
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        // Ensure poller is signaled to stop when GUI exits
-        if let Some(signal) = &self.poller_running_signal {
+        // Ensure poller is signaled to stop when GUI exits, keeping live changes
+        if let Some(signal) = self.poller_running_signal.clone() {
+            self.commit_target_ip();
+            if let Err(e) = self.config.save() {
+                eprintln!("Failed to save config: {:?}", e);
+            }
             signal.store(false, Ordering::SeqCst);
         }
         if let Some(handle) = self.poller_worker_handle.take() {
@@ -226,5 +833,141 @@ impl App for GuiApp {
                 println!("Input-Redirection thread joined on exit.");
             }
         }
+        // Let a replay send its neutral packets before the process ends
+        if let Some((stop, handle)) = self.replay.take() {
+            stop.store(false, Ordering::SeqCst);
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Outputs offered in the GUI, with file outputs written next to the config.
+fn output_choices() -> Vec<OutputConfig> {
+    let mut choices = vec![OutputConfig::Udp, OutputConfig::Stdout];
+    if let Ok(dir) = config_dir() {
+        choices.push(OutputConfig::Binary(dir.join("output.irs")));
+        choices.push(OutputConfig::JsonLines(dir.join("output.jsonl")));
+    }
+    choices
+}
+
+/// Deadzone, deadzone shape and inversion controls for one stick.
+fn stick_settings_ui(
+    ui: &mut egui::Ui,
+    deadzone: &mut f32,
+    outer_deadzone: &mut f32,
+    shape: &mut DeadzoneShape,
+    invert_x: &mut bool,
+    invert_y: &mut bool,
+) {
+    ui.label("Deadzone:");
+    ui.add(egui::Slider::new(deadzone, 0.0..=0.99).step_by(0.01));
+    ui.label("Outer Deadzone:");
+    ui.add(egui::Slider::new(outer_deadzone, 0.0..=0.5).step_by(0.01));
+    ui.horizontal(|ui| {
+        ui.label("Shape:");
+        ui.radio_value(shape, DeadzoneShape::Axial, "Axial");
+        ui.radio_value(shape, DeadzoneShape::Radial, "Radial");
+    });
+    ui.horizontal(|ui| {
+        ui.checkbox(invert_x, "Invert X");
+        ui.checkbox(invert_y, "Invert Y");
+    });
+}
+
+/// Response curve and sensitivity controls for one stick.
+/// Custom curves are only editable in config.toml; picking another curve replaces them.
+fn response_ui(ui: &mut egui::Ui, id: &str, curve: &mut ResponseCurve, sensitivity: &mut f32) {
+    ui.horizontal(|ui| {
+        ui.label("Curve:");
+        let selected = match curve {
+            ResponseCurve::Linear => "Linear".to_string(),
+            ResponseCurve::Exponential { .. } => "Exponential".to_string(),
+            ResponseCurve::Custom { points } => format!("Custom ({} points)", points.len()),
+        };
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                if ui
+                    .selectable_label(matches!(curve, ResponseCurve::Linear), "Linear")
+                    .clicked()
+                {
+                    *curve = ResponseCurve::Linear;
+                }
+                if ui
+                    .selectable_label(
+                        matches!(curve, ResponseCurve::Exponential { .. }),
+                        "Exponential",
+                    )
+                    .clicked()
+                    && !matches!(curve, ResponseCurve::Exponential { .. })
+                {
+                    *curve = ResponseCurve::Exponential { exponent: 2.0 };
+                }
+            });
+    });
+    if let ResponseCurve::Exponential { exponent } = curve {
+        ui.label("Exponent:");
+        ui.add(egui::Slider::new(exponent, 0.2..=5.0).step_by(0.1));
+    }
+    ui.label("Sensitivity:");
+    ui.add(egui::Slider::new(sensitivity, 0.1..=3.0).step_by(0.05));
+}
+
+/// Stick-to-D-pad and D-pad-to-circle-pad options.
+fn dpad_emulation_ui(ui: &mut egui::Ui, cfg: &mut AppConfig) {
+    let mut enabled = cfg.stick_to_dpad.is_some();
+    if ui.checkbox(&mut enabled, "Stick drives D-pad").changed() {
+        cfg.stick_to_dpad = enabled.then(StickToDpad::default);
+    }
+    if let Some(emu) = &mut cfg.stick_to_dpad {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut emu.stick, Stick::Left, "Left stick");
+            ui.radio_value(&mut emu.stick, Stick::Right, "Right stick");
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut emu.ways, DpadWays::Four, "4-way");
+            ui.radio_value(&mut emu.ways, DpadWays::Eight, "8-way");
+        });
+        ui.label("Threshold:");
+        ui.add(egui::Slider::new(&mut emu.threshold, 0.1..=1.0).step_by(0.01));
+    }
+    ui.checkbox(&mut cfg.dpad_to_circle_pad, "D-pad drives circle pad");
+}
+
+/// Per-button autofire rate and duty cycle, plus adding and removing turbo buttons.
+/// The toggle chord is only editable in config.toml.
+fn turbo_ui(ui: &mut egui::Ui, cfg: &mut AppConfig) {
+    let mut removed = None;
+    for (button, turbo) in cfg.turbo_buttons.iter_mut() {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut turbo.enabled, format!("{button:?}"));
+            if ui.small_button("Remove").clicked() {
+                removed = Some(*button);
+            }
+        });
+        ui.add(egui::Slider::new(&mut turbo.rate_hz, 1.0..=30.0).text("Hz"));
+        ui.add(egui::Slider::new(&mut turbo.duty_cycle, 0.1..=0.9).text("down"));
+    }
+    if let Some(button) = removed {
+        cfg.turbo_buttons.remove(&button);
+    }
+
+    egui::ComboBox::from_id_salt("turbo_add")
+        .selected_text("Add turbo button")
+        .show_ui(ui, |ui| {
+            for button in DsButton::ALL {
+                if !cfg.turbo_buttons.contains_key(&button)
+                    && ui.selectable_label(false, format!("{button:?}")).clicked()
+                {
+                    cfg.turbo_buttons.insert(button, Turbo::default());
+                }
+            }
+        });
+
+    if cfg.turbo_toggle_chord.is_empty() {
+        ui.small("No toggle chord; set turbo_toggle_chord in config.toml.");
+    } else {
+        ui.small(format!("Toggle chord: {:?}", cfg.turbo_toggle_chord));
     }
 }
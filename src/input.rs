@@ -0,0 +1,85 @@
+// input.rs
+use std::{
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use crate::{gamepad::GamepadSelector, mapping::GamepadButton};
+
+/// An analog axis, named like gilrs' but independent of any backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    /// Analog trigger travel, 0.0 released to 1.0 fully pressed.
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// Input normalized away from the device it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    /// The source started following this device.
+    Connected(String),
+    /// The followed device went away; everything it held counts as released.
+    Disconnected(String),
+    /// No device matches the selector; the source keeps waiting for one.
+    WaitingForGamepad(GamepadSelector),
+    Button(GamepadButton, bool),
+    Axis(InputAxis, f32),
+    /// A keyboard key, by egui key name, went down (`true`) or up.
+    Key(String, bool),
+    /// The touchscreen is held at this point (in screen pixels), or released (`None`).
+    Touch(Option<(u16, u16)>),
+}
+
+/// Something that produces input events for `PollerWorker`: a gamepad backend,
+/// the GUI's keyboard, a script, ...
+pub trait InputSource: Send {
+    /// Returns the next queued event without blocking.
+    fn poll_event(&mut self) -> Option<InputEvent>;
+
+    /// Waits up to `timeout` for the next event.
+    /// The default sleeps and then polls, for sources that cannot block.
+    fn wait_event(&mut self, timeout: Duration) -> Option<InputEvent> {
+        thread::sleep(timeout);
+        self.poll_event()
+    }
+
+    /// The configured gamepad changed. Sources that pick a device should switch to it.
+    fn select_gamepad(&mut self, _selector: &GamepadSelector) {}
+}
+
+/// A source fed through a channel, e.g. by the GUI's keyboard handling or a script.
+pub struct ChannelSource {
+    rx: mpsc::Receiver<InputEvent>,
+}
+
+impl ChannelSource {
+    /// Creates the source and the sender that feeds it.
+    pub fn new() -> (mpsc::Sender<InputEvent>, Self) {
+        let (tx, rx) = mpsc::channel();
+        (tx, Self { rx })
+    }
+}
+
+impl InputSource for ChannelSource {
+    fn poll_event(&mut self) -> Option<InputEvent> {
+        self.rx.try_recv().ok()
+    }
+
+    fn wait_event(&mut self, timeout: Duration) -> Option<InputEvent> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            // Nobody feeds us any more; don't turn the wait into a busy loop
+            Err(RecvTimeoutError::Disconnected) => {
+                thread::sleep(timeout);
+                None
+            }
+        }
+    }
+}
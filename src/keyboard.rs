@@ -0,0 +1,84 @@
+// keyboard.rs
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::pad_state::{DsButton, PadState};
+
+/// A direction a key pushes the circle pad in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// What holding a key does on the 3DS.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyTarget {
+    Button(DsButton),
+    /// Full circle-pad deflection; opposite keys cancel out, neighbouring ones make a diagonal.
+    CirclePad(Direction),
+}
+
+/// Keyboard key to 3DS table, keyed by egui key names ("A", "Up", "Enter", "1", ...).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct KeyMap(pub BTreeMap<String, KeyTarget>);
+
+// Default layout follows Citra's keyboard defaults, so it feels familiar.
+impl Default for KeyMap {
+    fn default() -> Self {
+        use DsButton as B;
+        use KeyTarget::{Button, CirclePad};
+        let pairs = [
+            ("A", Button(B::A)),
+            ("S", Button(B::B)),
+            ("Z", Button(B::X)),
+            ("X", Button(B::Y)),
+            ("Q", Button(B::L)),
+            ("W", Button(B::R)),
+            ("1", Button(B::ZL)),
+            ("2", Button(B::ZR)),
+            ("M", Button(B::Start)),
+            ("N", Button(B::Select)),
+            ("B", Button(B::Home)),
+            ("T", Button(B::DUp)),
+            ("G", Button(B::DDown)),
+            ("F", Button(B::DLeft)),
+            ("H", Button(B::DRight)),
+            ("Up", CirclePad(Direction::Up)),
+            ("Down", CirclePad(Direction::Down)),
+            ("Left", CirclePad(Direction::Left)),
+            ("Right", CirclePad(Direction::Right)),
+        ];
+        Self(
+            pairs
+                .into_iter()
+                .map(|(key, target)| (key.to_string(), target))
+                .collect(),
+        )
+    }
+}
+
+impl KeyMap {
+    /// Presses the buttons of the held keys on top of `st`, and moves the circle pad
+    /// if any circle-pad key is held. Unmapped keys are ignored.
+    pub fn apply<'a>(&self, held: impl IntoIterator<Item = &'a String>, st: &mut PadState) {
+        let (mut x, mut y) = (0.0f32, 0.0f32);
+        for target in held.into_iter().filter_map(|key| self.0.get(key)) {
+            match *target {
+                KeyTarget::Button(button) => st.set_button(button, true),
+                KeyTarget::CirclePad(Direction::Up) => y += 1.0,
+                KeyTarget::CirclePad(Direction::Down) => y -= 1.0,
+                KeyTarget::CirclePad(Direction::Left) => x -= 1.0,
+                KeyTarget::CirclePad(Direction::Right) => x += 1.0,
+            }
+        }
+
+        let magnitude = x.hypot(y);
+        if magnitude > 0.0 {
+            (st.lx, st.ly) = (x / magnitude, y / magnitude);
+        }
+    }
+}
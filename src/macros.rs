@@ -0,0 +1,157 @@
+// macros.rs
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{config::config_dir, mapping::GamepadButton, pad_state::PadState};
+
+/// A recorded macro and the gamepad chord that replays it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct MacroBinding {
+    /// File name of the recording in the `macros` directory, without extension.
+    pub name: String,
+    /// Gamepad buttons that start playback when held together.
+    pub chord: Vec<GamepadButton>,
+    /// Start over at the end instead of stopping.
+    pub looped: bool,
+    /// Stop playback as soon as the gamepad sends any other input.
+    pub cancel_on_input: bool,
+}
+
+/// The pad state from `at_ms` into the macro until the next frame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MacroFrame {
+    pub at_ms: u64,
+    pub state: PadState,
+}
+
+/// A recorded sequence of pad states with their timings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Macro {
+    /// Total length; the last frame is held until then.
+    pub length_ms: u64,
+    pub frames: Vec<MacroFrame>,
+}
+
+/// Path of a macro file, rejecting names that would leave the macros directory.
+fn macro_path(name: &str) -> io::Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ' '));
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid macro name '{name}'; use letters, digits, spaces, '-' and '_'"),
+        ));
+    }
+    Ok(config_dir()?.join("macros").join(format!("{name}.toml")))
+}
+
+impl Macro {
+    pub fn load(name: &str) -> io::Result<Self> {
+        let txt = fs::read_to_string(macro_path(name)?)?;
+        toml::from_str(&txt).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("TOML parse error: {e}"))
+        })
+    }
+
+    pub fn save(&self, name: &str) -> io::Result<()> {
+        let path = macro_path(name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let toml = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, toml)
+    }
+
+    /// The state `elapsed` into the macro, or `None` once it has ended.
+    pub fn state_at(&self, elapsed: Duration) -> Option<PadState> {
+        let ms = elapsed.as_millis() as u64;
+        if ms >= self.length_ms {
+            return None;
+        }
+        let frame = self.frames.iter().take_while(|f| f.at_ms <= ms).last();
+        Some(frame.map_or_else(PadState::new, |f| f.state))
+    }
+}
+
+/// Collects the pad state changes of a recording in progress.
+#[derive(Debug)]
+pub struct Recorder {
+    name: String,
+    started: Instant,
+    recording: Macro,
+}
+
+impl Recorder {
+    /// Starts recording into `name`; fails early if the name is unusable.
+    pub fn new(name: &str, now: Instant) -> io::Result<Self> {
+        macro_path(name)?;
+        Ok(Self {
+            name: name.to_string(),
+            started: now,
+            recording: Macro::default(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Adds a frame if `st` differs from the last one recorded.
+    pub fn record(&mut self, st: &PadState, now: Instant) {
+        if self.recording.frames.last().map(|f| &f.state) != Some(st) {
+            self.recording.frames.push(MacroFrame {
+                at_ms: now.duration_since(self.started).as_millis() as u64,
+                state: *st,
+            });
+        }
+    }
+
+    /// Ends the recording at `now` and saves it to the macros directory.
+    pub fn finish(mut self, now: Instant) -> io::Result<String> {
+        self.recording.length_ms = now.duration_since(self.started).as_millis() as u64;
+        self.recording.save(&self.name)?;
+        Ok(self.name)
+    }
+}
+
+/// A macro being replayed.
+#[derive(Debug)]
+pub struct Playback {
+    pub name: String,
+    recording: Arc<Macro>,
+    started: Instant,
+    looped: bool,
+    pub cancel_on_input: bool,
+}
+
+impl Playback {
+    pub fn new(binding: &MacroBinding, recording: Arc<Macro>, now: Instant) -> Self {
+        Self {
+            name: binding.name.clone(),
+            recording,
+            started: now,
+            looped: binding.looped,
+            cancel_on_input: binding.cancel_on_input,
+        }
+    }
+
+    /// The state to send at `now`, or `None` once a non-looping macro has ended.
+    pub fn state(&mut self, now: Instant) -> Option<PadState> {
+        if let Some(st) = self.recording.state_at(now.duration_since(self.started)) {
+            return Some(st);
+        }
+        if !self.looped || self.recording.length_ms == 0 {
+            return None;
+        }
+        self.started = now;
+        self.recording.state_at(Duration::ZERO)
+    }
+}
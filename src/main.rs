@@ -2,18 +2,78 @@
 mod cli_app;
 mod config;
 mod constants;
+mod dpad;
+mod error;
+mod gamepad;
 mod gui_app;
+mod input;
+mod keyboard;
+mod macros;
+mod mapping;
 mod network;
+mod output;
 mod pad_state;
 mod poller_worker;
+mod protocol;
+mod receiver;
+mod session;
+mod stick;
+mod touch;
+mod trigger;
+mod turbo;
 use cli_app::CliApp;
+use config::AppConfig;
 use eframe::{NativeOptions, egui};
 use gui_app::GuiApp;
+use output::OutputConfig;
+use receiver::Receiver;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 fn main() -> eframe::Result<()> {
     // If the app is runned with --gui flag, run the GUI app
-    Ok(if std::env::args().any(|arg| arg == "--cli") {
-        let _ = CliApp::new().run();
+    if std::env::args().any(|arg| arg == "--receiver") {
+        // Act as a local 3DS: decode and print whatever a sender points at this machine
+        if let Err(e) = run_receiver() {
+            eprintln!("Receiver failed: {e}");
+        }
+    } else if let Some(path) = arg_value("--replay") {
+        // Send a recorded session to the configured target instead of live input
+        if let Err(e) = run_replay(&path) {
+            eprintln!("Replay failed: {e}");
+            std::process::exit(1);
+        }
+    } else if std::env::args().any(|arg| arg == "--list-gamepads") {
+        if let Err(e) = CliApp::list_gamepads() {
+            eprintln!("{e}");
+            std::process::exit(e.exit_code());
+        }
+    } else if std::env::args().any(|arg| arg == "--cli") {
+        // --output udp|stdout|binary:<path>|jsonl:<path> overrides the configured output
+        let output = match arg_value("--output").map(|s| s.parse::<OutputConfig>()) {
+            Some(Err(e)) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            output => output.and_then(Result::ok),
+        };
+        match CliApp::new(arg_value("--record-session").map(PathBuf::from), output) {
+            Ok(mut app) => {
+                if let Err(e) = app.run() {
+                    eprintln!("Input-Redirection failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(e.exit_code());
+            }
+        }
     } else {
         const ICON_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/app_icon.rgba"));
 
@@ -29,7 +89,7 @@ fn main() -> eframe::Result<()> {
 
         let options = NativeOptions {
             viewport: egui::ViewportBuilder::default()
-                .with_inner_size([350.0, 400.0])
+                .with_inner_size([350.0, 520.0])
                 .with_resizable(false)
                 .with_icon(icon),
             ..Default::default()
@@ -40,5 +100,49 @@ fn main() -> eframe::Result<()> {
             options,
             Box::new(|_cc| Ok(Box::new(GuiApp::new()) as Box<dyn eframe::App>)),
         );
-    })
+    }
+    Ok(())
+}
+
+/// Value following `flag` on the command line, e.g. the path in `--replay path`.
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    args.next()
+}
+
+/// Replays a session file to the configured target, at `--speed` (default 1) and
+/// over and over with `--loop`, until done or Ctrl+C.
+fn run_replay(path: &str) -> anyhow::Result<()> {
+    let speed = match arg_value("--speed") {
+        Some(s) => s
+            .parse::<f32>()
+            .ok()
+            .filter(|v| *v > 0.0)
+            .ok_or_else(|| anyhow::anyhow!("invalid --speed '{s}'"))?,
+        None => 1.0,
+    };
+    let looped = std::env::args().any(|arg| arg == "--loop");
+    let target_ip = AppConfig::load().unwrap_or_default().target_ip;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || r.store(false, Ordering::SeqCst))?;
+
+    println!(
+        "Replaying {path} to {target_ip} at {speed}x{}",
+        if looped { ", looped" } else { "" }
+    );
+    session::replay_file(Path::new(path), &target_ip, speed, looped, &running, |e| {
+        eprintln!("Failed to send packet: {e}")
+    })?;
+    println!("Replay finished.");
+    Ok(())
+}
+
+fn run_receiver() -> anyhow::Result<()> {
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || r.store(false, Ordering::SeqCst))?;
+    Receiver::new(running)?.run()
 }
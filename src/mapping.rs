@@ -0,0 +1,185 @@
+// mapping.rs
+use gilrs::Button as GilrsButton;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::pad_state::{DsButton, PadState};
+
+/// A physical gamepad button, named after gilrs' layout-agnostic positions.
+/// Kept separate from `gilrs::Button` so it can be stored in the config.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    C,
+    Z,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 19] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::North,
+        GamepadButton::West,
+        GamepadButton::C,
+        GamepadButton::Z,
+        GamepadButton::LeftTrigger,
+        GamepadButton::LeftTrigger2,
+        GamepadButton::RightTrigger,
+        GamepadButton::RightTrigger2,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::Mode,
+        GamepadButton::LeftThumb,
+        GamepadButton::RightThumb,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+
+    pub fn from_gilrs(btn: GilrsButton) -> Option<Self> {
+        use GilrsButton::*;
+        Some(match btn {
+            South => Self::South,
+            East => Self::East,
+            North => Self::North,
+            West => Self::West,
+            C => Self::C,
+            Z => Self::Z,
+            LeftTrigger => Self::LeftTrigger,
+            LeftTrigger2 => Self::LeftTrigger2,
+            RightTrigger => Self::RightTrigger,
+            RightTrigger2 => Self::RightTrigger2,
+            Select => Self::Select,
+            Start => Self::Start,
+            Mode => Self::Mode,
+            LeftThumb => Self::LeftThumb,
+            RightThumb => Self::RightThumb,
+            DPadUp => Self::DPadUp,
+            DPadDown => Self::DPadDown,
+            DPadLeft => Self::DPadLeft,
+            DPadRight => Self::DPadRight,
+            Unknown => return None,
+        })
+    }
+}
+
+/// What a gamepad button drives: a 3DS button, written as its name (`"A"`),
+/// or a touch hotspot, written as `{ touch = "<hotspot name>" }`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MapTarget {
+    Button(DsButton),
+    Touch { touch: String },
+}
+
+/// Gamepad button to 3DS button table.
+/// Several gamepad buttons may drive the same 3DS button, and one gamepad
+/// button may drive several 3DS buttons (and touch hotspots) at once.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct ButtonMap(pub BTreeMap<GamepadButton, Vec<MapTarget>>);
+
+// Default layout: Nintendo-style positions, so South is B and East is A.
+impl Default for ButtonMap {
+    fn default() -> Self {
+        use GamepadButton as G;
+        let pairs = [
+            (G::South, DsButton::B),
+            (G::East, DsButton::A),
+            (G::West, DsButton::X),
+            (G::North, DsButton::Y),
+            (G::DPadUp, DsButton::DUp),
+            (G::DPadDown, DsButton::DDown),
+            (G::DPadLeft, DsButton::DLeft),
+            (G::DPadRight, DsButton::DRight),
+            (G::Select, DsButton::Select),
+            (G::Start, DsButton::Start),
+            (G::Mode, DsButton::Home),
+            (G::LeftTrigger, DsButton::L),
+            (G::RightTrigger, DsButton::R),
+            (G::LeftTrigger2, DsButton::ZL),
+            (G::RightTrigger2, DsButton::ZR),
+        ];
+        Self(
+            pairs
+                .into_iter()
+                .map(|(g, ds)| (g, vec![MapTarget::Button(ds)]))
+                .collect(),
+        )
+    }
+}
+
+impl ButtonMap {
+    /// Rebuilds the digital buttons of `st` from the set of held gamepad buttons.
+    /// Recomputing from scratch keeps a 3DS button pressed for as long as any
+    /// gamepad button mapped to it is still held.
+    pub fn apply<'a>(&self, held: impl IntoIterator<Item = &'a GamepadButton>, st: &mut PadState) {
+        st.release_buttons();
+        for btn in held {
+            for target in self.0.get(btn).into_iter().flatten() {
+                if let MapTarget::Button(target) = target {
+                    st.set_button(*target, true);
+                }
+            }
+        }
+    }
+
+    /// Names of the touch hotspots `btn` is mapped to.
+    pub fn hotspots(&self, btn: &GamepadButton) -> impl Iterator<Item = &str> {
+        self.0
+            .get(btn)
+            .into_iter()
+            .flatten()
+            .filter_map(|target| match target {
+                MapTarget::Touch { touch } => Some(touch.as_str()),
+                MapTarget::Button(_) => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_parse_as_buttons_or_touch_hotspots() {
+        let map: ButtonMap = toml::from_str(r#"South = ["A", { touch = "x" }]"#).unwrap();
+        assert_eq!(
+            map.0[&GamepadButton::South],
+            [
+                MapTarget::Button(DsButton::A),
+                MapTarget::Touch { touch: "x".into() },
+            ]
+        );
+        assert_eq!(
+            map.hotspots(&GamepadButton::South).collect::<Vec<_>>(),
+            ["x"]
+        );
+
+        let mut st = PadState::new();
+        map.apply([&GamepadButton::South], &mut st);
+        assert!(st.is_pressed(DsButton::A));
+    }
+
+    #[test]
+    fn unknown_button_names_are_rejected() {
+        assert!(toml::from_str::<ButtonMap>(r#"South = ["Touch"]"#).is_err());
+    }
+}
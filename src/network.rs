@@ -1,79 +1,57 @@
-use crate::{constants::*, pad_state::PadState};
-use byteorder::{LittleEndian, WriteBytesExt};
-use std::{io::Cursor, net::UdpSocket, time::SystemTime}; // Added SystemTime
+use crate::{constants::*, error::StartError, pad_state::PadState, protocol::Packet};
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
 
 pub struct Sender {
     sock: UdpSocket,
-    target_ip: String,
-    // No longer need last_sent_state
+    target: SocketAddr,
 }
 
 impl Sender {
-    pub fn new(target_ip: String) -> Self {
-        Self {
-            sock: UdpSocket::bind("0.0.0.0:0").expect("Failed to bind UDP socket"),
-            target_ip,
-            // No last_sent_state initialization needed
-        }
+    /// Resolves `target_ip` once and binds a local socket of the matching address family.
+    pub fn new(target_ip: &str) -> Result<Self, StartError> {
+        let target = resolve_target(target_ip).map_err(|source| StartError::Resolve {
+            target: target_ip.to_string(),
+            source,
+        })?;
+        let bind_addr = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        Ok(Self {
+            sock: UdpSocket::bind(bind_addr).map_err(StartError::Bind)?,
+            target,
+        })
     }
 
-    pub fn send_state(&mut self, st: &PadState) {
-        if cfg!(debug_assertions) {
-            println!(
-                "{} Sending state (always): {:?}",
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs(),
-                st
-            );
-        }
-
-        let mut buf = [0u8; 20];
-        let mut cursor = Cursor::new(&mut buf[..]);
-
-        // Your existing battle-tested serialization logic
-        let _ = cursor.write_u32::<LittleEndian>(st.buttons);
-        let _ = cursor.write_u32::<LittleEndian>(0x0200_0000);
-
-        let x_cpad = (st.lx * CPAD_BOUND + CPAD_CENTER_OFFSET_INT as f32) as i32;
-        let y_cpad = (st.ly * CPAD_BOUND + CPAD_CENTER_OFFSET_INT as f32) as i32;
-        let circle_payload = (clamp_u12(y_cpad) << 12) | clamp_u12(x_cpad);
-        let _ = cursor.write_u32::<LittleEndian>(circle_payload);
-
-        let calculated_rx = (st.rx + st.ry) * ROT_CPP_BOUND + CPP_CENTER_OFFSET_INT as f32;
-        let calculated_ry = (st.ry - st.rx) * ROT_CPP_BOUND + CPP_CENTER_OFFSET_INT as f32;
-        let c_stick_payload = (clamp_u8(calculated_ry as i32) << 24)
-            | (clamp_u8(calculated_rx as i32) << 16)
-            | ((st.ir_buttons as u32) << 8)
-            | 0x81;
-        let _ = cursor.write_u32::<LittleEndian>(c_stick_payload);
-
-        let _ = cursor.write_u32::<LittleEndian>(0);
+    /// Encodes and sends one packet. Failures are returned for the caller to report.
+    pub fn send_state(&mut self, st: &PadState) -> io::Result<()> {
+        self.send_packet(Packet::from_state(st))
+    }
 
-        // Attempt to send
-        if let Err(e) = self.sock.send_to(&buf, (&*self.target_ip, TARGET_PORT)) {
-            // Still good to log errors if they occur, even if we don't change behavior based on them here
-            eprintln!(
-                "{} Failed to send UDP packet to {}:{}: {}",
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs(),
-                self.target_ip,
-                TARGET_PORT,
-                e
-            );
-        }
+    /// Sends an already built packet as-is, e.g. one replayed from a session file.
+    pub fn send_packet(&mut self, packet: Packet) -> io::Result<()> {
+        self.sock.send_to(&packet.encode(), self.target).map(|_| ())
     }
-}
 
-#[inline]
-fn clamp_u12(x: i32) -> u32 {
-    x.clamp(0, 0xFFF) as u32
+    /// The resolved endpoint packets are sent to.
+    pub fn target(&self) -> SocketAddr {
+        self.target
+    }
 }
 
-#[inline]
-fn clamp_u8(x: i32) -> u32 {
-    x.clamp(0, 0xFF) as u32
+/// Resolves a host name or IP literal to the 3DS input redirection endpoint.
+pub fn resolve_target(target_ip: &str) -> io::Result<SocketAddr> {
+    (target_ip.trim(), TARGET_PORT)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{target_ip}' did not resolve to any address"),
+            )
+        })
 }
@@ -0,0 +1,199 @@
+// output.rs
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Instant,
+};
+
+use crate::{
+    error::StartError, network::Sender, pad_state::PadState, protocol::Packet,
+    session::SessionWriter,
+};
+
+/// Where the worker delivers the pad state each tick.
+pub trait OutputSink: Send {
+    /// Delivers one state; `packet` is its wire encoding.
+    fn send(&mut self, state: &PadState, packet: Packet) -> io::Result<()>;
+
+    /// Where the output goes, for status messages.
+    fn describe(&self) -> String;
+}
+
+/// Which sink to use, as stored in the config and given to `--output`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputConfig {
+    /// Send packets to the 3DS at `target_ip`.
+    #[default]
+    Udp,
+    /// Dry run: print each change of state instead of sending anything.
+    Stdout,
+    /// Write timestamped packets in the session file format, replayable with `--replay`.
+    Binary(PathBuf),
+    /// Write one JSON object per packet, with the state and its encoding.
+    JsonLines(PathBuf),
+}
+
+impl OutputConfig {
+    /// Opens the sink. `target_ip` is only used by the UDP sink.
+    pub fn open(&self, target_ip: &str) -> Result<Box<dyn OutputSink>, StartError> {
+        Ok(match self {
+            OutputConfig::Udp => Box::new(Sender::new(target_ip)?),
+            OutputConfig::Stdout => Box::new(StdoutSink::default()),
+            OutputConfig::Binary(path) => Box::new(
+                SessionWriter::create(path, Instant::now())
+                    .map_err(|source| StartError::Output(path.clone(), source))?,
+            ),
+            OutputConfig::JsonLines(path) => Box::new(
+                JsonLinesSink::create(path)
+                    .map_err(|source| StartError::Output(path.clone(), source))?,
+            ),
+        })
+    }
+}
+
+/// Parses the `--output` argument: `udp`, `stdout`, `binary:<path>` or `jsonl:<path>`.
+impl FromStr for OutputConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s.eq_ignore_ascii_case("udp") => Ok(OutputConfig::Udp),
+            None if s.eq_ignore_ascii_case("stdout") => Ok(OutputConfig::Stdout),
+            Some(("binary", path)) if !path.is_empty() => Ok(OutputConfig::Binary(path.into())),
+            Some(("jsonl", path)) if !path.is_empty() => Ok(OutputConfig::JsonLines(path.into())),
+            _ => Err(format!(
+                "invalid output '{s}'; expected udp, stdout, binary:<path> or jsonl:<path>"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputConfig::Udp => write!(f, "UDP"),
+            OutputConfig::Stdout => write!(f, "stdout (dry run)"),
+            OutputConfig::Binary(path) => write!(f, "binary file {}", path.display()),
+            OutputConfig::JsonLines(path) => write!(f, "JSON lines file {}", path.display()),
+        }
+    }
+}
+
+impl OutputSink for Sender {
+    fn send(&mut self, _state: &PadState, packet: Packet) -> io::Result<()> {
+        self.send_packet(packet)
+    }
+
+    fn describe(&self) -> String {
+        self.target().to_string()
+    }
+}
+
+impl OutputSink for SessionWriter {
+    fn send(&mut self, _state: &PadState, packet: Packet) -> io::Result<()> {
+        self.write(packet, Instant::now())
+    }
+
+    fn describe(&self) -> String {
+        self.path().display().to_string()
+    }
+}
+
+/// Prints every change of state, like `--receiver` would show it.
+#[derive(Default)]
+pub struct StdoutSink {
+    last: Option<Packet>,
+}
+
+impl OutputSink for StdoutSink {
+    fn send(&mut self, state: &PadState, packet: Packet) -> io::Result<()> {
+        if self.last != Some(packet) {
+            println!("[dry run] {state}");
+            self.last = Some(packet);
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        "stdout (dry run)".to_string()
+    }
+}
+
+/// Writes one JSON object per packet: milliseconds since start, the state and the packet words.
+pub struct JsonLinesSink {
+    out: BufWriter<File>,
+    path: PathBuf,
+    started: Instant,
+}
+
+impl JsonLinesSink {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            path: path.to_path_buf(),
+            started: Instant::now(),
+        })
+    }
+}
+
+impl OutputSink for JsonLinesSink {
+    fn send(&mut self, st: &PadState, packet: Packet) -> io::Result<()> {
+        let touch = if st.touch_pressed {
+            format!("[{},{}]", st.touch_x, st.touch_y)
+        } else {
+            "null".to_string()
+        };
+        writeln!(
+            self.out,
+            concat!(
+                r#"{{"t_ms":{},"lx":{},"ly":{},"rx":{},"ry":{},"buttons":{},"ir_buttons":{},"#,
+                r#""interface_buttons":{},"touch":{},"packet":[{},{},{},{},{}]}}"#
+            ),
+            self.started.elapsed().as_millis(),
+            st.lx,
+            st.ly,
+            st.rx,
+            st.ry,
+            st.buttons,
+            st.ir_buttons,
+            st.interface_buttons,
+            touch,
+            packet.hid,
+            packet.touch,
+            packet.circle_pad,
+            packet.cpp,
+            packet.interface,
+        )
+    }
+
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Keeps every state sent, for inspecting the worker's output in tests.
+/// Clones share the same buffer, so one can stay with the test while the other is handed over.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    pub sent: std::sync::Arc<std::sync::Mutex<Vec<(PadState, Packet)>>>,
+}
+
+#[cfg(test)]
+impl OutputSink for MemorySink {
+    fn send(&mut self, state: &PadState, packet: Packet) -> io::Result<()> {
+        self.sent
+            .lock()
+            .map_err(|_| io::Error::other("memory sink poisoned"))?
+            .push((*state, packet));
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        "memory".to_string()
+    }
+}
@@ -1,8 +1,77 @@
 // pad_state.rs
 use crate::constants::*;
-use gilrs::Axis;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+// Button names for the `Display` summary at the bottom of this file
+const HID_NAMES: [(u32, &str); 12] = [
+    (hid_bits::A, "A"),
+    (hid_bits::B, "B"),
+    (hid_bits::X, "X"),
+    (hid_bits::Y, "Y"),
+    (hid_bits::L, "L"),
+    (hid_bits::R, "R"),
+    (hid_bits::START, "START"),
+    (hid_bits::SELECT, "SELECT"),
+    (hid_bits::DUP, "UP"),
+    (hid_bits::DDOWN, "DOWN"),
+    (hid_bits::DLEFT, "LEFT"),
+    (hid_bits::DRIGHT, "RIGHT"),
+];
+
+const IR_NAMES: [(u8, &str); 2] = [(ir_bits::ZL, "ZL"), (ir_bits::ZR, "ZR")];
+
+const IFACE_NAMES: [(u8, &str); 3] = [
+    (iface_bits::HOME, "HOME"),
+    (iface_bits::POWER, "POWER"),
+    (iface_bits::POWER_LONG, "POWER_LONG"),
+];
+
+/// A button on the 3DS side: HID buttons, ZL/ZR (IR) or an interface button.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DsButton {
+    A,
+    B,
+    X,
+    Y,
+    L,
+    R,
+    ZL,
+    ZR,
+    Start,
+    Select,
+    DUp,
+    DDown,
+    DLeft,
+    DRight,
+    Home,
+    Power,
+    PowerLong,
+}
+
+impl DsButton {
+    pub const ALL: [DsButton; 17] = [
+        DsButton::A,
+        DsButton::B,
+        DsButton::X,
+        DsButton::Y,
+        DsButton::L,
+        DsButton::R,
+        DsButton::ZL,
+        DsButton::ZR,
+        DsButton::Start,
+        DsButton::Select,
+        DsButton::DUp,
+        DsButton::DDown,
+        DsButton::DLeft,
+        DsButton::DRight,
+        DsButton::Home,
+        DsButton::Power,
+        DsButton::PowerLong,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub struct PadState {
     pub lx: f32,
     pub ly: f32,
@@ -10,6 +79,10 @@ pub struct PadState {
     pub ry: f32,
     pub buttons: u32,
     pub ir_buttons: u8,
+    pub interface_buttons: u8,
+    pub touch_pressed: bool,
+    pub touch_x: u16,
+    pub touch_y: u16,
 }
 
 impl PadState {
@@ -21,52 +94,138 @@ impl PadState {
             ry: MINIMAL_NUDGE_RSTICK,
             buttons: 0xFFF,
             ir_buttons: 0,
+            interface_buttons: 0,
+            touch_pressed: false,
+            touch_x: 0,
+            touch_y: 0,
         }
     }
 
-    /// Apply dead-zone plus optional inversion to one stick axis.
-    /// Returns `true` if the stored value was changed.
-    pub fn apply_axis(&mut self, axis: Axis, value: f32, inverted: bool, deadzone: f32) -> bool {
-        let in_val = if inverted { -value } else { value };
-
-        let adjusted = if in_val.abs() < deadzone {
-            match axis {
-                Axis::RightStickX | Axis::RightStickY => {
-                    if in_val >= 0.0 {
-                        MINIMAL_NUDGE_RSTICK
-                    } else {
-                        -MINIMAL_NUDGE_RSTICK
-                    }
-                }
-                _ => {
-                    if in_val >= 0.0 {
-                        MINIMAL_NUDGE_LSTICK
-                    } else {
-                        -MINIMAL_NUDGE_LSTICK
-                    }
+    /// Press or release one 3DS button, whichever bitfield it lives in.
+    pub fn set_button(&mut self, button: DsButton, pressed: bool) {
+        // HID buttons: pressed clears bit, released sets bit
+        macro_rules! hid {
+            ($bit:expr) => {
+                if pressed {
+                    self.buttons &= !(1 << $bit);
+                } else {
+                    self.buttons |= 1 << $bit;
                 }
-            }
-        } else {
-            in_val
-        };
-
-        macro_rules! update {
-            ($field:expr) => {{
-                if ($field - adjusted).abs() > f32::EPSILON {
-                    $field = adjusted;
-                    true
+            };
+        }
+        // IR and interface buttons: pressed sets bit, released clears bit
+        macro_rules! active_high {
+            ($field:expr, $bit:expr) => {
+                if pressed {
+                    $field |= 1 << $bit;
                 } else {
-                    false
+                    $field &= !(1 << $bit);
                 }
-            }};
+            };
         }
 
-        match axis {
-            Axis::LeftStickX => update!(self.lx),
-            Axis::LeftStickY => update!(self.ly),
-            Axis::RightStickX => update!(self.rx),
-            Axis::RightStickY => update!(self.ry),
-            _ => false,
+        match button {
+            DsButton::A => hid!(hid_bits::A),
+            DsButton::B => hid!(hid_bits::B),
+            DsButton::X => hid!(hid_bits::X),
+            DsButton::Y => hid!(hid_bits::Y),
+            DsButton::L => hid!(hid_bits::L),
+            DsButton::R => hid!(hid_bits::R),
+            DsButton::Start => hid!(hid_bits::START),
+            DsButton::Select => hid!(hid_bits::SELECT),
+            DsButton::DUp => hid!(hid_bits::DUP),
+            DsButton::DDown => hid!(hid_bits::DDOWN),
+            DsButton::DLeft => hid!(hid_bits::DLEFT),
+            DsButton::DRight => hid!(hid_bits::DRIGHT),
+            DsButton::ZL => active_high!(self.ir_buttons, ir_bits::ZL),
+            DsButton::ZR => active_high!(self.ir_buttons, ir_bits::ZR),
+            DsButton::Home => active_high!(self.interface_buttons, iface_bits::HOME),
+            DsButton::Power => active_high!(self.interface_buttons, iface_bits::POWER),
+            DsButton::PowerLong => active_high!(self.interface_buttons, iface_bits::POWER_LONG),
+        }
+    }
+
+    /// Whether a 3DS button is currently pressed, whichever bitfield it lives in.
+    pub fn is_pressed(&self, button: DsButton) -> bool {
+        let hid = |bit: u32| self.buttons & (1 << bit) == 0;
+        let ir = |bit: u8| self.ir_buttons & (1 << bit) != 0;
+        let iface = |bit: u8| self.interface_buttons & (1 << bit) != 0;
+
+        match button {
+            DsButton::A => hid(hid_bits::A),
+            DsButton::B => hid(hid_bits::B),
+            DsButton::X => hid(hid_bits::X),
+            DsButton::Y => hid(hid_bits::Y),
+            DsButton::L => hid(hid_bits::L),
+            DsButton::R => hid(hid_bits::R),
+            DsButton::Start => hid(hid_bits::START),
+            DsButton::Select => hid(hid_bits::SELECT),
+            DsButton::DUp => hid(hid_bits::DUP),
+            DsButton::DDown => hid(hid_bits::DDOWN),
+            DsButton::DLeft => hid(hid_bits::DLEFT),
+            DsButton::DRight => hid(hid_bits::DRIGHT),
+            DsButton::ZL => ir(ir_bits::ZL),
+            DsButton::ZR => ir(ir_bits::ZR),
+            DsButton::Home => iface(iface_bits::HOME),
+            DsButton::Power => iface(iface_bits::POWER),
+            DsButton::PowerLong => iface(iface_bits::POWER_LONG),
+        }
+    }
+
+    /// Whether nothing is pressed, touched or deflected past the at-rest nudge.
+    pub fn is_neutral(&self) -> bool {
+        self.buttons == 0xFFF
+            && self.ir_buttons == 0
+            && self.interface_buttons == 0
+            && !self.touch_pressed
+            && self.lx.abs() <= MINIMAL_NUDGE_LSTICK
+            && self.ly.abs() <= MINIMAL_NUDGE_LSTICK
+            && self.rx.abs() <= MINIMAL_NUDGE_RSTICK
+            && self.ry.abs() <= MINIMAL_NUDGE_RSTICK
+    }
+
+    /// Release every digital button, leaving sticks and touch untouched.
+    pub fn release_buttons(&mut self) {
+        self.buttons = 0xFFF;
+        self.ir_buttons = 0;
+        self.interface_buttons = 0;
+    }
+}
+
+/// One-line summary: sticks, touch and the names of every pressed button.
+impl fmt::Display for PadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pressed: Vec<&str> = Vec::new();
+        // HID buttons are active low, IR and interface buttons active high.
+        pressed.extend(
+            HID_NAMES
+                .iter()
+                .filter(|(bit, _)| self.buttons & (1 << bit) == 0)
+                .map(|(_, n)| *n),
+        );
+        pressed.extend(
+            IR_NAMES
+                .iter()
+                .filter(|(bit, _)| self.ir_buttons & (1 << bit) != 0)
+                .map(|(_, n)| *n),
+        );
+        pressed.extend(
+            IFACE_NAMES
+                .iter()
+                .filter(|(bit, _)| self.interface_buttons & (1 << bit) != 0)
+                .map(|(_, n)| *n),
+        );
+
+        write!(
+            f,
+            "cpad ({:+.2}, {:+.2}) cstick ({:+.2}, {:+.2}) touch ",
+            self.lx, self.ly, self.rx, self.ry
+        )?;
+        if self.touch_pressed {
+            write!(f, "({:3}, {:3})", self.touch_x, self.touch_y)?;
+        } else {
+            write!(f, "none")?;
         }
+        write!(f, " buttons [{}]", pressed.join(" "))
     }
 }
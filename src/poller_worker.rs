@@ -1,180 +1,973 @@
 // poller_worker.rs
-use gilrs::{Axis, Button as GilrsButton, EventType, GamepadId, Gilrs};
 use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
+        mpsc,
     },
-    time::Duration,
+    thread,
+    time::{Duration, Instant},
 };
 
 // Assuming these modules are accessible from the crate root (e.g., `crate::constants`)
 // If your project structure is different, you might need to adjust these paths.
 use crate::{
-    constants::{hid_bits, ir_bits},
-    network::Sender,
-    pad_state::PadState,
+    config::AppConfig,
+    constants::{
+        MINIMAL_NUDGE_LSTICK, MINIMAL_NUDGE_RSTICK, RELEASE_REPEAT, TOUCH_SCREEN_HEIGHT,
+        TOUCH_SCREEN_WIDTH,
+    },
+    dpad::{StickToDpad, dpad_to_circle_pad},
+    error::StartError,
+    gamepad::GamepadSelector,
+    input::{InputAxis, InputEvent, InputSource},
+    keyboard::KeyMap,
+    macros::{Macro, MacroBinding, Playback, Recorder},
+    mapping::{ButtonMap, GamepadButton},
+    output::{OutputConfig, OutputSink},
+    pad_state::{DsButton, PadState},
+    protocol::Packet,
+    session::SessionWriter,
+    stick::StickSettings,
+    touch::{Taps, TouchHotspot},
+    trigger::TriggerSettings,
+    turbo::{Autofire, Turbo},
 };
 
+/// Upper bound for `send_rate_hz`; Luma cannot consume packets much faster than this.
+const MAX_SEND_RATE_HZ: u32 = 1000;
+
+/// Longest the loop blocks waiting for input before re-checking the `running` flag.
+const MAX_IDLE_WAIT: Duration = Duration::from_millis(50);
+
+/// How often packet statistics are reported to the front-end.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stick axes in the order their raw values are kept in `PollerWorker::raw_sticks`.
+const STICK_AXES: [InputAxis; 4] = [
+    InputAxis::LeftStickX,
+    InputAxis::LeftStickY,
+    InputAxis::RightStickX,
+    InputAxis::RightStickY,
+];
+
+/// Analog trigger axes and the gamepad button each one presses past its threshold.
+const TRIGGER_AXES: [(InputAxis, GamepadButton); 2] = [
+    (InputAxis::LeftTrigger, GamepadButton::LeftTrigger2),
+    (InputAxis::RightTrigger, GamepadButton::RightTrigger2),
+];
+
 /// Configuration for the PollerWorker.
 /// This struct holds the necessary configuration values that were previously
 /// part of AppConfig and directly used by the polling logic.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct PollerConfig {
     pub target_ip: String,
-    pub deadzone_lstick: f32,
-    pub deadzone_rstick: f32,
-    pub invert_lx: bool,
-    pub invert_ly: bool,
-    pub invert_rx: bool,
-    pub invert_ry: bool,
+    pub output: OutputConfig,
+    pub gamepad: GamepadSelector,
+    pub lstick: StickSettings,
+    pub rstick: StickSettings,
+    pub triggers: TriggerSettings,
+    pub stick_to_dpad: Option<StickToDpad>,
+    pub dpad_to_circle_pad: bool,
+    pub touch_hotspots: Vec<TouchHotspot>,
+    pub button_map: ButtonMap,
+    pub turbo_buttons: BTreeMap<DsButton, Turbo>,
+    pub turbo_toggle_chord: Vec<GamepadButton>,
+    pub key_map: KeyMap,
+    pub macros: Vec<MacroBinding>,
+    pub send_rate_hz: u32,
+}
+
+impl PollerConfig {
+    /// The hotspot called `name`; mappings to unknown names do nothing.
+    fn hotspot(&self, name: &str) -> Option<&TouchHotspot> {
+        self.touch_hotspots.iter().find(|h| h.name == name)
+    }
+}
+
+impl From<&AppConfig> for PollerConfig {
+    fn from(cfg: &AppConfig) -> Self {
+        Self {
+            target_ip: cfg.target_ip.clone(),
+            output: cfg.output.clone(),
+            gamepad: cfg.gamepad.clone(),
+            lstick: StickSettings {
+                invert_x: cfg.invert_lx,
+                invert_y: cfg.invert_ly,
+                deadzone: cfg.deadzone_lstick,
+                outer_deadzone: cfg.outer_deadzone_lstick,
+                shape: cfg.deadzone_shape_lstick,
+                curve: cfg.curve_lstick.sorted(),
+                sensitivity: cfg.sensitivity_lstick,
+            },
+            rstick: StickSettings {
+                invert_x: cfg.invert_rx,
+                invert_y: cfg.invert_ry,
+                deadzone: cfg.deadzone_rstick,
+                outer_deadzone: cfg.outer_deadzone_rstick,
+                shape: cfg.deadzone_shape_rstick,
+                curve: cfg.curve_rstick.sorted(),
+                sensitivity: cfg.sensitivity_rstick,
+            },
+            triggers: TriggerSettings {
+                press_threshold: cfg.trigger_press_threshold,
+                release_threshold: cfg.trigger_release_threshold,
+            },
+            stick_to_dpad: cfg.stick_to_dpad,
+            dpad_to_circle_pad: cfg.dpad_to_circle_pad,
+            touch_hotspots: cfg.touch_hotspots.clone(),
+            button_map: cfg.button_map.clone(),
+            turbo_buttons: cfg.turbo_buttons.clone(),
+            turbo_toggle_chord: cfg.turbo_toggle_chord.clone(),
+            key_map: cfg.key_map.clone(),
+            macros: cfg.macros.clone(),
+            send_rate_hz: cfg.send_rate_hz,
+        }
+    }
+}
+
+/// Status updates the worker reports back to the front-end that spawned it.
+#[derive(Clone, Debug)]
+pub enum WorkerStatus {
+    /// The worker loop is running and sending to this output.
+    Started(String),
+    /// The output or target was changed live and packets now go here.
+    OutputChanged(String),
+    /// No connected gamepad matches the selector; the worker keeps waiting for one.
+    WaitingForGamepad(GamepadSelector),
+    GamepadConnected(String),
+    GamepadDisconnected(String),
+    /// The turbo hotkey chord switched autofire on (`true`) or off.
+    TurboToggled(bool),
+    RecordingStarted(String),
+    /// A recording was saved to the macros directory under this name.
+    MacroSaved(String),
+    MacroStarted(String),
+    MacroStopped(String),
+    /// A macro could not be loaded, recorded or saved.
+    MacroFailed(String),
+    SessionStarted(PathBuf),
+    /// Session recording stopped and the file was written completely.
+    SessionSaved(PathBuf),
+    /// The session file could not be created or written; recording stopped.
+    SessionFailed(String),
+    /// A live configuration change could not be applied; the previous value is kept.
+    ConfigRejected(String),
+    /// Sending failed. Repeats of the same error are only reported once.
+    SendError(String),
+    /// Periodic statistics: packets actually sent over the last interval and the latest state.
+    Stats {
+        packets_per_sec: u32,
+        state: PadState,
+    },
+}
+
+/// Commands a front-end can send to a running worker.
+#[derive(Clone, Debug)]
+pub enum WorkerCommand {
+    /// Replace the worker configuration without restarting it.
+    /// Boxed to keep the other commands small.
+    UpdateConfig(Box<PollerConfig>),
+    /// Start recording the pad state into the named macro.
+    StartRecording(String),
+    /// Stop recording and save the macro.
+    StopRecording,
+    /// Start writing every sent packet to a session file.
+    StartSession(PathBuf),
+    /// Stop writing the session file.
+    StopSession,
+}
+
+impl fmt::Display for WorkerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerStatus::WaitingForGamepad(selector) => write!(f, "Waiting for {selector}..."),
+            WorkerStatus::GamepadConnected(name) => write!(f, "Gamepad '{name}' connected"),
+            WorkerStatus::GamepadDisconnected(name) => write!(f, "Gamepad '{name}' disconnected"),
+            WorkerStatus::Started(output) => write!(f, "Started; sending to {output}"),
+            WorkerStatus::OutputChanged(output) => write!(f, "Now sending to {output}"),
+            WorkerStatus::TurboToggled(on) => {
+                write!(f, "Turbo {}", if *on { "on" } else { "off" })
+            }
+            WorkerStatus::RecordingStarted(name) => write!(f, "Recording macro '{name}'..."),
+            WorkerStatus::MacroSaved(name) => write!(f, "Macro '{name}' saved"),
+            WorkerStatus::MacroStarted(name) => write!(f, "Playing macro '{name}'"),
+            WorkerStatus::MacroStopped(name) => write!(f, "Macro '{name}' stopped"),
+            WorkerStatus::MacroFailed(e) => write!(f, "Macro error: {e}"),
+            WorkerStatus::SessionStarted(path) => {
+                write!(f, "Recording session to {}", path.display())
+            }
+            WorkerStatus::SessionSaved(path) => write!(f, "Session saved to {}", path.display()),
+            WorkerStatus::SessionFailed(e) => write!(f, "Session recording failed: {e}"),
+            WorkerStatus::ConfigRejected(reason) => write!(f, "Config not applied: {reason}"),
+            WorkerStatus::SendError(e) => write!(f, "Failed to send packet: {e}"),
+            WorkerStatus::Stats {
+                packets_per_sec,
+                state,
+            } => write!(f, "{packets_per_sec} packets/s, {state}"),
+        }
+    }
 }
 
-/// PollerWorker handles gamepad event polling and state sending in a separate thread.
+/// PollerWorker handles input polling and state sending in a separate thread.
 pub struct PollerWorker {
-    gilrs: Gilrs,
-    active_id: GamepadId,
+    /// Where input comes from. The loop blocks on the first one between ticks
+    /// and polls the rest, so it should be the busiest.
+    sources: Vec<Box<dyn InputSource>>,
     cfg: PollerConfig,
     state: PadState,
-    sender: Sender,
+    held: BTreeSet<GamepadButton>,
+    /// Keyboard keys held, by egui key name.
+    keys: BTreeSet<String>,
+    /// Where the touchscreen is held, if it is.
+    touch: Option<(u16, u16)>,
+    /// Touches of the hotspots with a hold duration.
+    taps: Taps,
+    /// Whether each trigger axis in `TRIGGER_AXES` is currently past its threshold.
+    triggers: [bool; 2],
+    /// Chords (turbo toggle and macro triggers) fully held as of the last button change.
+    held_chords: Vec<Vec<GamepadButton>>,
+    /// Buttons of chords that fired. They stay out of the button map until each
+    /// is released, so letting go of a chord one button at a time presses nothing.
+    consumed: BTreeSet<GamepadButton>,
+    /// Whether turbo buttons autofire; flipped by the toggle chord.
+    turbo_on: bool,
+    autofire: Autofire,
+    /// Recordings of `cfg.macros`, by index; `None` where loading failed.
+    macros: Vec<Option<Arc<Macro>>>,
+    recorder: Option<Recorder>,
+    playback: Option<Playback>,
+    session: Option<SessionWriter>,
+    /// Last raw value of each stick axis; sticks are re-derived from these on every change.
+    raw_sticks: [f32; 4],
+    sink: Box<dyn OutputSink>,
+    /// Packets sent since `stats_since`, for the periodic `Stats` report.
+    packets_sent: u32,
+    stats_since: Instant,
+    /// Last send error reported, so a persistent failure is reported once rather than every tick.
+    last_send_error: Option<String>,
     running: Arc<AtomicBool>,
+    status_tx: mpsc::Sender<WorkerStatus>,
+    command_tx: mpsc::Sender<WorkerCommand>,
+    command_rx: mpsc::Receiver<WorkerCommand>,
 }
 
 impl PollerWorker {
     pub fn new(
-        gilrs: Gilrs,
-        active_id: GamepadId,
+        sources: Vec<Box<dyn InputSource>>,
         config: PollerConfig,
         running: Arc<AtomicBool>,
-    ) -> Self {
-        let sender = Sender::new(config.target_ip.clone());
+        status_tx: mpsc::Sender<WorkerStatus>,
+    ) -> Result<Self, StartError> {
+        let sink = config.output.open(&config.target_ip)?;
         let state = PadState::new();
+        let (command_tx, command_rx) = mpsc::channel();
 
-        PollerWorker {
-            gilrs,
-            active_id,
+        Ok(PollerWorker {
+            sources,
             cfg: config,
             state,
-            sender,
+            held: BTreeSet::new(),
+            keys: BTreeSet::new(),
+            touch: None,
+            taps: Taps::default(),
+            triggers: [false; 2],
+            held_chords: Vec::new(),
+            consumed: BTreeSet::new(),
+            turbo_on: true,
+            autofire: Autofire::default(),
+            macros: Vec::new(),
+            recorder: None,
+            playback: None,
+            session: None,
+            raw_sticks: [0.0; 4],
+            sink,
+            packets_sent: 0,
+            stats_since: Instant::now(),
+            last_send_error: None,
             running,
-        }
+            status_tx,
+            command_tx,
+            command_rx,
+        })
     }
 
-    /// Updates the button bitfields based on press/release events.
-    /// The logic is identical to the original implementation in CliApp.
-    fn update_button_state(&mut self, btn: GilrsButton, pressed: bool) {
-        let hid = &mut self.state.buttons;
-        let ir = &mut self.state.ir_buttons;
+    /// Returns a handle for sending commands to this worker once it runs in its thread.
+    pub fn commands(&self) -> mpsc::Sender<WorkerCommand> {
+        self.command_tx.clone()
+    }
 
-        // Macro for HID buttons (pressed clears bit, released sets bit)
-        macro_rules! set_bit {
-            ($map:expr, $bit:expr) => {
-                if pressed {
-                    *$map &= !(1 << $bit);
-                } else {
-                    *$map |= 1 << $bit;
+    /// Applies every command queued by the front-end.
+    fn process_commands(&mut self) {
+        while let Ok(cmd) = self.command_rx.try_recv() {
+            match cmd {
+                WorkerCommand::UpdateConfig(cfg) => self.apply_config(*cfg),
+                WorkerCommand::StartRecording(name) => self.start_recording(&name),
+                WorkerCommand::StopRecording => self.stop_recording(),
+                WorkerCommand::StartSession(path) => self.start_session(&path),
+                WorkerCommand::StopSession => self.stop_session(),
+            }
+        }
+    }
+
+    /// Swaps in a new configuration, re-deriving everything that depends on it.
+    fn apply_config(&mut self, mut cfg: PollerConfig) {
+        // The target only matters to the UDP sink
+        let target_changed = cfg.target_ip != self.cfg.target_ip && cfg.output == OutputConfig::Udp;
+        if cfg.output != self.cfg.output || target_changed {
+            match cfg.output.open(&cfg.target_ip) {
+                Ok(sink) => {
+                    // Nothing should stay held on the output being left behind
+                    let neutral = PadState::new();
+                    for _ in 0..RELEASE_REPEAT {
+                        let _ = self.sink.send(&neutral, Packet::from_state(&neutral));
+                    }
+                    self.report(WorkerStatus::OutputChanged(sink.describe()));
+                    self.sink = sink;
+                    self.last_send_error = None;
                 }
-            };
-        }
-
-        use GilrsButton::*;
-        match btn {
-            South => set_bit!(hid, hid_bits::B),
-            East => set_bit!(hid, hid_bits::A),
-            West => set_bit!(hid, hid_bits::X),
-            North => set_bit!(hid, hid_bits::Y),
-            DPadUp => set_bit!(hid, hid_bits::DUP),
-            DPadDown => set_bit!(hid, hid_bits::DDOWN),
-            DPadLeft => set_bit!(hid, hid_bits::DLEFT),
-            DPadRight => set_bit!(hid, hid_bits::DRIGHT),
-            Select => set_bit!(hid, hid_bits::SELECT),
-            Start | Mode => set_bit!(hid, hid_bits::START),
-            LeftTrigger => set_bit!(hid, hid_bits::L),
-            RightTrigger => set_bit!(hid, hid_bits::R),
-            LeftTrigger2 => {
-                // IR Button: pressed sets bit, released clears bit
-                if pressed {
-                    *ir |= 1 << ir_bits::ZL;
-                } else {
-                    *ir &= !(1 << ir_bits::ZL);
+                Err(e) => {
+                    self.report(WorkerStatus::ConfigRejected(e.to_string()));
+                    cfg.output = self.cfg.output.clone();
+                    cfg.target_ip = self.cfg.target_ip.clone();
                 }
             }
-            RightTrigger2 => {
-                // IR Button: pressed sets bit, released clears bit
-                if pressed {
-                    *ir |= 1 << ir_bits::ZR;
-                } else {
-                    *ir &= !(1 << ir_bits::ZR);
+        }
+
+        let selector_changed = cfg.gamepad != self.cfg.gamepad;
+        let macros_changed = cfg.macros != self.cfg.macros;
+        self.cfg = cfg;
+        if macros_changed {
+            self.load_macros();
+        }
+
+        if selector_changed {
+            for source in &mut self.sources {
+                source.select_gamepad(&self.cfg.gamepad);
+            }
+        }
+
+        // Re-derive the pad state from the raw inputs under the new settings
+        self.update_state();
+    }
+
+    /// Forwards a status update; a front-end that stopped listening is not an error.
+    fn report(&self, status: WorkerStatus) {
+        let _ = self.status_tx.send(status);
+    }
+
+    /// Releases what the followed gamepad held if it went away.
+    /// Keyboard keys and touches come from other sources and stay held.
+    fn on_disconnected(&mut self, name: String) {
+        self.release_gamepad();
+        self.update_state();
+        for _ in 0..RELEASE_REPEAT {
+            self.send();
+        }
+        self.report(WorkerStatus::GamepadDisconnected(name));
+    }
+
+    /// Tracks the held gamepad buttons and rebuilds the 3DS button bitfields
+    /// through the configured button map.
+    fn update_button_state(&mut self, btn: GamepadButton, pressed: bool) {
+        if pressed {
+            self.held.insert(btn);
+        } else {
+            self.held.remove(&btn);
+            self.consumed.remove(&btn);
+        }
+        self.update_chords();
+        // Like the button map, hotspots ignore a press that completed a chord
+        if pressed && !self.consumed.contains(&btn) {
+            let now = Instant::now();
+            for name in self.cfg.button_map.hotspots(&btn) {
+                if let Some(hotspot) = self.cfg.hotspot(name) {
+                    self.taps.start(hotspot, now);
+                }
+            }
+        }
+        self.update_state();
+    }
+
+    /// Acts on every chord whose last button just went down:
+    /// the turbo chord flips turbo, macro chords start their macro.
+    fn update_chords(&mut self) {
+        let is_held = |chord: &[GamepadButton]| {
+            !chord.is_empty() && chord.iter().all(|b| self.held.contains(b))
+        };
+        let held: Vec<_> = std::iter::once(&self.cfg.turbo_toggle_chord)
+            .chain(self.cfg.macros.iter().map(|m| &m.chord))
+            .filter(|chord| is_held(chord))
+            .cloned()
+            .collect();
+        let pressed: Vec<_> = held
+            .iter()
+            .filter(|chord| !self.held_chords.contains(chord))
+            .cloned()
+            .collect();
+        self.held_chords = held;
+
+        for chord in pressed {
+            self.consumed.extend(chord.iter().copied());
+            if chord == self.cfg.turbo_toggle_chord {
+                self.turbo_on = !self.turbo_on;
+                self.autofire.reset();
+                self.report(WorkerStatus::TurboToggled(self.turbo_on));
+            }
+            if let Some(i) = self.cfg.macros.iter().position(|m| m.chord == chord) {
+                self.start_macro(i);
+            }
+        }
+    }
+
+    /// Loads the recording of every bound macro, reporting the ones that fail.
+    fn load_macros(&mut self) {
+        self.macros = self
+            .cfg
+            .macros
+            .iter()
+            .map(|binding| match Macro::load(&binding.name) {
+                Ok(recording) => Some(Arc::new(recording)),
+                Err(e) => {
+                    self.report(WorkerStatus::MacroFailed(format!(
+                        "'{}': {e}",
+                        binding.name
+                    )));
+                    None
                 }
+            })
+            .collect();
+    }
+
+    /// Starts replaying `cfg.macros[i]`, replacing any macro already playing.
+    fn start_macro(&mut self, i: usize) {
+        let Some(recording) = self.macros.get(i).cloned().flatten() else {
+            return;
+        };
+        self.stop_macro();
+        let playback = Playback::new(&self.cfg.macros[i], recording, Instant::now());
+        self.report(WorkerStatus::MacroStarted(playback.name.clone()));
+        self.playback = Some(playback);
+    }
+
+    fn stop_macro(&mut self) {
+        if let Some(playback) = self.playback.take() {
+            self.report(WorkerStatus::MacroStopped(playback.name));
+        }
+    }
+
+    fn start_recording(&mut self, name: &str) {
+        match Recorder::new(name, Instant::now()) {
+            Ok(recorder) => {
+                self.report(WorkerStatus::RecordingStarted(recorder.name().to_string()));
+                self.recorder = Some(recorder);
+            }
+            Err(e) => self.report(WorkerStatus::MacroFailed(e.to_string())),
+        }
+    }
+
+    /// Saves the recording in progress and reloads the bound macros, which may include it.
+    fn stop_recording(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        match recorder.finish(Instant::now()) {
+            Ok(name) => {
+                self.report(WorkerStatus::MacroSaved(name));
+                self.load_macros();
+            }
+            Err(e) => self.report(WorkerStatus::MacroFailed(e.to_string())),
+        }
+    }
+
+    /// Presses or releases a trigger's gamepad button as its axis crosses the thresholds.
+    fn update_trigger(&mut self, i: usize, value: f32) {
+        let pressed = self.cfg.triggers.update(self.triggers[i], value);
+        if pressed != self.triggers[i] {
+            self.triggers[i] = pressed;
+            self.update_state();
+        }
+    }
+
+    /// Rebuilds the whole pad state from the raw inputs: buttons, then sticks, then keyboard
+    /// keys and touch on top, then the D-pad emulation options, which read and overwrite all of them.
+    fn update_state(&mut self) {
+        self.update_buttons();
+        self.update_sticks();
+        self.cfg.key_map.apply(&self.keys, &mut self.state);
+        self.update_touch();
+        if let Some(emu) = &self.cfg.stick_to_dpad {
+            emu.apply(&mut self.state);
+        }
+        if self.cfg.dpad_to_circle_pad {
+            dpad_to_circle_pad(&mut self.state);
+        }
+        if self.playback.as_ref().is_some_and(|p| p.cancel_on_input) && !self.state.is_neutral() {
+            self.stop_macro();
+        }
+    }
+
+    /// Rebuilds the 3DS buttons from the held buttons plus the pressed trigger axes.
+    fn update_buttons(&mut self) {
+        let triggered = TRIGGER_AXES
+            .iter()
+            .zip(self.triggers)
+            .filter(|(_, pressed)| *pressed)
+            .map(|((_, btn), _)| btn);
+        let held = self.held.iter().filter(|b| !self.consumed.contains(b));
+        self.cfg
+            .button_map
+            .apply(held.chain(triggered), &mut self.state);
+    }
+
+    /// Runs the raw stick values through each stick's settings into the pad state.
+    /// Both axes are processed together so radial deadzones see the whole stick.
+    fn update_sticks(&mut self) {
+        let [lx, ly, rx, ry] = self.raw_sticks;
+        (self.state.lx, self.state.ly) = self.cfg.lstick.process(lx, ly, MINIMAL_NUDGE_LSTICK);
+        (self.state.rx, self.state.ry) = self.cfg.rstick.process(rx, ry, MINIMAL_NUDGE_RSTICK);
+    }
+
+    /// Puts the touch point, clamped to the screen, into the pad state: the one held
+    /// through a touch event, else a hotspot whose button is held, else the latest tap.
+    fn update_touch(&mut self) {
+        let held_hotspot = self
+            .held
+            .iter()
+            .filter(|b| !self.consumed.contains(b))
+            .flat_map(|b| self.cfg.button_map.hotspots(b))
+            .filter_map(|name| self.cfg.hotspot(name))
+            .find(|hotspot| hotspot.hold_ms.is_none())
+            .map(TouchHotspot::point);
+        let touch = self.touch.or(held_hotspot).or(self.taps.current());
+        self.state.touch_pressed = touch.is_some();
+        let (x, y) = touch.unwrap_or_default();
+        self.state.touch_x = x.min(TOUCH_SCREEN_WIDTH - 1);
+        self.state.touch_y = y.min(TOUCH_SCREEN_HEIGHT - 1);
+    }
+
+    /// Starts a new session file, ending any session already being recorded.
+    fn start_session(&mut self, path: &Path) {
+        self.stop_session();
+        match SessionWriter::create(path, Instant::now()) {
+            Ok(session) => {
+                self.report(WorkerStatus::SessionStarted(session.path().to_path_buf()));
+                self.session = Some(session);
+            }
+            Err(e) => self.report(WorkerStatus::SessionFailed(format!(
+                "{}: {e}",
+                path.display()
+            ))),
+        }
+    }
+
+    fn stop_session(&mut self) {
+        if let Some(session) = self.session.take() {
+            match session.finish() {
+                Ok(path) => self.report(WorkerStatus::SessionSaved(path)),
+                Err(e) => self.report(WorkerStatus::SessionFailed(e.to_string())),
             }
-            _ => {}
         }
     }
 
-    /// Returns whether the axis should be inverted based on the worker's configuration.
-    #[inline]
-    fn axis_inverted(&self, axis: Axis) -> bool {
-        match axis {
-            Axis::LeftStickX => self.cfg.invert_lx,
-            Axis::LeftStickY => self.cfg.invert_ly,
-            Axis::RightStickX => self.cfg.invert_rx,
-            Axis::RightStickY => self.cfg.invert_ry,
-            _ => false,
+    /// Forgets the gamepad's buttons, chords, triggers and sticks.
+    fn release_gamepad(&mut self) {
+        self.held.clear();
+        self.held_chords.clear();
+        self.consumed.clear();
+        self.triggers = [false; 2];
+        self.raw_sticks = [0.0; 4];
+    }
+
+    /// Resets the pad to its neutral state and sends it, so nothing stays held on the 3DS.
+    fn release_all(&mut self) {
+        self.release_gamepad();
+        self.keys.clear();
+        self.touch = None;
+        self.taps.reset();
+        self.autofire.reset();
+        self.stop_macro();
+        self.state = PadState::new();
+        for _ in 0..RELEASE_REPEAT {
+            self.send();
+        }
+    }
+
+    /// Sends the current state, or the playing macro's, with autofire applied.
+    /// Reports (but does not repeat) failures.
+    fn send(&mut self) {
+        let now = Instant::now();
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&self.state, now);
+        }
+
+        let mut out = self.state;
+        if let Some(playback) = &mut self.playback {
+            match playback.state(now) {
+                Some(st) => out = st,
+                None => self.stop_macro(),
+            }
+        }
+        if self.turbo_on {
+            self.autofire.apply(&self.cfg.turbo_buttons, &mut out, now);
+        }
+        let packet = Packet::from_state(&out);
+        if let Some(session) = &mut self.session
+            && let Err(e) = session.write(packet, now)
+        {
+            self.session = None;
+            self.report(WorkerStatus::SessionFailed(e.to_string()));
+        }
+        match self.sink.send(&out, packet) {
+            Ok(()) => {
+                self.packets_sent += 1;
+                self.last_send_error = None;
+            }
+            Err(e) => {
+                let msg = e.to_string();
+                if self.last_send_error.as_ref() != Some(&msg) {
+                    self.report(WorkerStatus::SendError(msg.clone()));
+                    self.last_send_error = Some(msg);
+                }
+            }
+        }
+        // Only after sending, so even a tap shorter than a tick reaches the 3DS once
+        if self.taps.expire(now) {
+            self.update_state();
         }
     }
 
-    /// Returns the deadzone value for the given axis based on the worker's configuration.
-    #[inline]
-    fn deadzone(&self, axis: Axis) -> f32 {
-        match axis {
-            Axis::LeftStickX | Axis::LeftStickY => self.cfg.deadzone_lstick,
-            Axis::RightStickX | Axis::RightStickY => self.cfg.deadzone_rstick,
-            _ => 0.0,
+    /// Reports packets/sec and the current state once per `STATS_INTERVAL`.
+    fn report_stats(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.stats_since);
+        if elapsed < STATS_INTERVAL {
+            return;
         }
+        let packets_per_sec = (self.packets_sent as f32 / elapsed.as_secs_f32()).round() as u32;
+        self.report(WorkerStatus::Stats {
+            packets_per_sec,
+            state: self.state,
+        });
+        self.packets_sent = 0;
+        self.stats_since = now;
     }
 
     /// Runs the main event polling and state sending loop.
     /// This method is intended to be run in a separate thread.
+    /// The neutral state is sent on the way out, whether the loop stopped or panicked
+    /// (release builds unwind for this; see `panic` in Cargo.toml).
     pub fn run(&mut self) {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.poll_loop()));
+        // Keep a recording that was still going when the worker stopped
+        self.stop_recording();
+        self.release_all();
+        // After the release, so replays end neutral too
+        self.stop_session();
+        if let Err(payload) = result {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Applies one input event to the pad state.
+    fn handle_event(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Connected(name) => self.report(WorkerStatus::GamepadConnected(name)),
+            InputEvent::Disconnected(name) => self.on_disconnected(name),
+            InputEvent::WaitingForGamepad(selector) => {
+                self.report(WorkerStatus::WaitingForGamepad(selector))
+            }
+            InputEvent::Axis(axis, value) => {
+                if let Some(i) = STICK_AXES.iter().position(|a| *a == axis) {
+                    self.raw_sticks[i] = value;
+                    self.update_state();
+                } else if let Some(i) = TRIGGER_AXES.iter().position(|(a, _)| *a == axis) {
+                    self.update_trigger(i, value);
+                }
+            }
+            InputEvent::Button(b, pressed) => self.update_button_state(b, pressed),
+            InputEvent::Key(name, pressed) => {
+                if pressed {
+                    self.keys.insert(name);
+                } else {
+                    self.keys.remove(&name);
+                }
+                self.update_state();
+            }
+            InputEvent::Touch(touch) => {
+                self.touch = touch;
+                self.update_state();
+            }
+        }
+    }
+
+    /// Takes the next queued event from any source, without blocking.
+    fn poll_sources(&mut self) -> Option<InputEvent> {
+        self.sources
+            .iter_mut()
+            .find_map(|source| source.poll_event())
+    }
+
+    /// Sends exactly one packet per tick at `send_rate_hz`, folding in every event
+    /// that arrived since the previous tick.
+    fn poll_loop(&mut self) {
+        self.report(WorkerStatus::Started(self.sink.describe()));
+        self.load_macros();
+
+        let mut next_tick = Instant::now();
+        self.stats_since = next_tick;
+
         while self.running.load(Ordering::SeqCst) {
-            let mut event_processed_and_state_changed = false;
-
-            // Block-wait for events with a timeout (original code used 5ms in the example)
-            // This allows the loop to periodically check the `running` flag.
-            if let Some(evt) = self
-                .gilrs
-                .next_event_blocking(Some(Duration::from_millis(16)))
-            {
-                if evt.id == self.active_id {
-                    match evt.event {
-                        EventType::AxisChanged(axis, value, _) => {
-                            let deadzone_val = self.deadzone(axis);
-                            let inv = self.axis_inverted(axis);
-                            if self.state.apply_axis(axis, value, inv, deadzone_val) {
-                                event_processed_and_state_changed = true;
-                            }
-                        }
-                        EventType::ButtonPressed(b, _) => {
-                            self.update_button_state(b, true);
-                            event_processed_and_state_changed = true;
-                        }
-                        EventType::ButtonReleased(b, _) => {
-                            self.update_button_state(b, false);
-                            event_processed_and_state_changed = true;
-                        }
-                        EventType::Connected | EventType::Disconnected => {
-                            println!("Input-Redirection: Gamepad {:?} event: {:?}", evt.id, evt.event);
-                        }
-                        _ => {} // Other event types are ignored
-                    }
+            self.process_commands();
+
+            // Drain everything already queued before deciding whether to send.
+            while let Some(event) = self.poll_sources() {
+                self.handle_event(event);
+            }
 
-                    if event_processed_and_state_changed {
-                        self.sender.send_state(&self.state);
+            let now = Instant::now();
+            if now >= next_tick {
+                self.send();
+                self.report_stats(now);
+                // Read the rate every tick so live changes take effect immediately
+                let period =
+                    Duration::from_secs(1) / self.cfg.send_rate_hz.clamp(1, MAX_SEND_RATE_HZ);
+                next_tick += period;
+                if next_tick < now {
+                    // We fell behind (e.g. the thread was descheduled); don't burst to catch up.
+                    next_tick = now + period;
+                }
+                continue;
+            }
+
+            // Wait for input until the next tick. The cap keeps the `running` flag
+            // responsive at low send rates.
+            let wait = (next_tick - now).min(MAX_IDLE_WAIT);
+            match self.sources.first_mut() {
+                Some(source) => {
+                    if let Some(event) = source.wait_event(wait) {
+                        self.handle_event(event);
                     }
                 }
+                None => thread::sleep(wait),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        input::ChannelSource,
+        macros::{MacroBinding, MacroFrame},
+        mapping::MapTarget,
+        output::MemorySink,
+    };
+
+    /// A worker fed by a `ChannelSource` and sending into a `MemorySink`.
+    struct Harness {
+        worker: PollerWorker,
+        input: mpsc::Sender<InputEvent>,
+        sink: MemorySink,
+        _status: mpsc::Receiver<WorkerStatus>,
+    }
+
+    impl Harness {
+        fn new(mut cfg: AppConfig) -> Self {
+            // Stdout opens without touching the network; it is swapped out right away
+            cfg.output = OutputConfig::Stdout;
+            let (input, source) = ChannelSource::new();
+            let (status_tx, status) = mpsc::channel();
+            let mut worker = PollerWorker::new(
+                vec![Box::new(source)],
+                PollerConfig::from(&cfg),
+                Arc::new(AtomicBool::new(true)),
+                status_tx,
+            )
+            .unwrap();
+            let sink = MemorySink::default();
+            worker.sink = Box::new(sink.clone());
+            Self {
+                worker,
+                input,
+                sink,
+                _status: status,
             }
-            // Unconditionally send state to ensure regular updates, as per original logic.
-            self.sender.send_state(&self.state);
         }
+
+        /// Feeds `events` to the worker, then runs one send tick and returns what it sent.
+        fn tick(&mut self, events: impl IntoIterator<Item = InputEvent>) -> PadState {
+            for event in events {
+                self.input.send(event).unwrap();
+            }
+            while let Some(event) = self.worker.poll_sources() {
+                self.worker.handle_event(event);
+            }
+            self.worker.send();
+            self.last_sent()
+        }
+
+        fn last_sent(&self) -> PadState {
+            self.sink.sent.lock().unwrap().last().unwrap().0
+        }
+    }
+
+    #[test]
+    fn channel_source_input_reaches_the_sink() {
+        let mut h = Harness::new(AppConfig::default());
+        // Nintendo-style default layout: East is A
+        let st = h.tick([InputEvent::Button(GamepadButton::East, true)]);
+        assert!(st.is_pressed(DsButton::A));
+        let st = h.tick([InputEvent::Button(GamepadButton::East, false)]);
+        assert!(st.is_neutral());
+    }
+
+    #[test]
+    fn output_change_releases_the_old_sink() {
+        let mut h = Harness::new(AppConfig::default());
+        h.tick([InputEvent::Button(GamepadButton::East, true)]);
+
+        let mut cfg = h.worker.cfg.clone();
+        cfg.output = OutputConfig::Udp;
+        cfg.target_ip = "127.0.0.1".into();
+        h.worker.apply_config(cfg);
+
+        let sent = h.sink.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1 + RELEASE_REPEAT);
+        assert!(sent[1..].iter().all(|(st, _)| st.is_neutral()));
+    }
+
+    #[test]
+    fn released_chord_buttons_stay_hidden() {
+        use GamepadButton::{Select, South};
+        let mut h = Harness::new(AppConfig {
+            turbo_toggle_chord: vec![Select, South],
+            ..Default::default()
+        });
+        let turbo_on = h.worker.turbo_on;
+
+        let st = h.tick([
+            InputEvent::Button(Select, true),
+            InputEvent::Button(South, true),
+        ]);
+        assert_ne!(h.worker.turbo_on, turbo_on);
+        assert!(!st.is_pressed(DsButton::Select) && !st.is_pressed(DsButton::B));
+        // Letting go of Select first must not turn the still-held South into a B press
+        let st = h.tick([InputEvent::Button(Select, false)]);
+        assert!(!st.is_pressed(DsButton::B));
+        // Once released on its own, the button works normally again
+        h.tick([InputEvent::Button(South, false)]);
+        let st = h.tick([InputEvent::Button(South, true)]);
+        assert!(st.is_pressed(DsButton::B));
+    }
+
+    #[test]
+    fn releasing_the_chord_does_not_cancel_its_macro() {
+        use GamepadButton::{Select, South};
+        let mut h = Harness::new(AppConfig {
+            macros: vec![MacroBinding {
+                name: "test".into(),
+                chord: vec![Select, South],
+                looped: true,
+                cancel_on_input: true,
+            }],
+            ..Default::default()
+        });
+        let mut state = PadState::new();
+        state.set_button(DsButton::X, true);
+        h.worker.macros = vec![Some(Arc::new(Macro {
+            length_ms: 1000,
+            frames: vec![MacroFrame { at_ms: 0, state }],
+        }))];
+
+        let st = h.tick([
+            InputEvent::Button(Select, true),
+            InputEvent::Button(South, true),
+        ]);
+        assert!(st.is_pressed(DsButton::X));
+        h.tick([InputEvent::Button(Select, false)]);
+        let st = h.tick([InputEvent::Button(South, false)]);
+        assert!(h.worker.playback.is_some());
+        assert!(st.is_pressed(DsButton::X));
+        // Any other input still cancels it
+        h.tick([InputEvent::Button(GamepadButton::East, true)]);
+        assert!(h.worker.playback.is_none());
+    }
+
+    #[test]
+    fn gamepad_disconnect_keeps_keyboard_and_touch() {
+        let mut h = Harness::new(AppConfig::default());
+        h.tick([
+            InputEvent::Button(GamepadButton::South, true),
+            InputEvent::Key("A".into(), true),
+            InputEvent::Touch(Some((100, 50))),
+        ]);
+        let st = h.tick([InputEvent::Disconnected("pad".into())]);
+        assert!(!st.is_pressed(DsButton::B));
+        assert!(st.is_pressed(DsButton::A));
+        assert!(st.touch_pressed);
+    }
+
+    /// Maps `button` to a hotspot at (10, 20) held for `hold_ms`.
+    fn with_hotspot(button: GamepadButton, hold_ms: Option<u64>) -> AppConfig {
+        let mut cfg = AppConfig {
+            touch_hotspots: vec![TouchHotspot {
+                name: "spot".into(),
+                x: 10,
+                y: 20,
+                hold_ms,
+            }],
+            ..Default::default()
+        };
+        cfg.button_map.0.insert(
+            button,
+            vec![MapTarget::Touch {
+                touch: "spot".into(),
+            }],
+        );
+        cfg
+    }
+
+    #[test]
+    fn zero_length_tap_is_sent_once() {
+        let mut h = Harness::new(with_hotspot(GamepadButton::C, Some(0)));
+        let st = h.tick([
+            InputEvent::Button(GamepadButton::C, true),
+            InputEvent::Button(GamepadButton::C, false),
+        ]);
+        assert!(st.touch_pressed);
+        assert_eq!((st.touch_x, st.touch_y), (10, 20));
+        assert!(!h.tick([]).touch_pressed);
+    }
+
+    #[test]
+    fn chord_press_does_not_tap() {
+        use GamepadButton::{C, Select};
+        let mut cfg = with_hotspot(C, Some(1000));
+        cfg.turbo_toggle_chord = vec![Select, C];
+        let mut h = Harness::new(cfg);
+        let st = h.tick([
+            InputEvent::Button(Select, true),
+            InputEvent::Button(C, true),
+        ]);
+        assert!(!st.touch_pressed);
+    }
+
+    #[test]
+    fn held_hotspot_touches_while_held() {
+        let mut h = Harness::new(with_hotspot(GamepadButton::C, None));
+        assert!(
+            h.tick([InputEvent::Button(GamepadButton::C, true)])
+                .touch_pressed
+        );
+        assert!(
+            !h.tick([InputEvent::Button(GamepadButton::C, false)])
+                .touch_pressed
+        );
     }
 }
@@ -0,0 +1,266 @@
+// protocol.rs
+use crate::{constants::*, pad_state::PadState};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Cursor};
+
+/// Size in bytes of one input redirection packet.
+pub const PACKET_LEN: usize = 20;
+
+/// Low byte of the C-stick word; tells Luma the C-stick/ZL/ZR fields are valid.
+pub const CPP_MAGIC: u32 = 0x81;
+
+/// One input redirection packet, as the five little-endian words Luma expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    /// HID buttons, active low (a cleared bit means pressed).
+    pub hid: u32,
+    /// Touchscreen: `TOUCH_NONE`, or the pressed flag plus 12-bit y and x.
+    pub touch: u32,
+    /// Circle pad: 12-bit y and x around `CPAD_CENTER_OFFSET_INT`.
+    pub circle_pad: u32,
+    /// C-stick (rotated 45°), ZL/ZR bits and `CPP_MAGIC`.
+    pub cpp: u32,
+    /// Interface buttons (HOME, POWER, POWER long-press).
+    pub interface: u32,
+}
+
+impl Packet {
+    /// Builds the packet describing `st`.
+    pub fn from_state(st: &PadState) -> Self {
+        let touch = if st.touch_pressed {
+            let x = st.touch_x.min(TOUCH_SCREEN_WIDTH) as u32 * 0xFFF / TOUCH_SCREEN_WIDTH as u32;
+            let y = st.touch_y.min(TOUCH_SCREEN_HEIGHT) as u32 * 0xFFF / TOUCH_SCREEN_HEIGHT as u32;
+            TOUCH_PRESSED_FLAG | (y << 12) | x
+        } else {
+            TOUCH_NONE
+        };
+
+        let x_cpad = (st.lx * CPAD_BOUND + CPAD_CENTER_OFFSET_INT as f32) as i32;
+        let y_cpad = (st.ly * CPAD_BOUND + CPAD_CENTER_OFFSET_INT as f32) as i32;
+        let circle_pad = (clamp_u12(y_cpad) << 12) | clamp_u12(x_cpad);
+
+        // The C-stick is reported rotated by 45°, hence the sum/difference of the axes.
+        let calculated_rx = (st.rx + st.ry) * ROT_CPP_BOUND + CPP_CENTER_OFFSET_INT as f32;
+        let calculated_ry = (st.ry - st.rx) * ROT_CPP_BOUND + CPP_CENTER_OFFSET_INT as f32;
+        let cpp = (clamp_u8(calculated_ry as i32) << 24)
+            | (clamp_u8(calculated_rx as i32) << 16)
+            | ((st.ir_buttons as u32) << 8)
+            | CPP_MAGIC;
+
+        Self {
+            hid: st.buttons,
+            touch,
+            circle_pad,
+            cpp,
+            interface: st.interface_buttons as u32,
+        }
+    }
+
+    /// Reconstructs the pad state carried by this packet.
+    /// Stick and touch values are quantised on the wire, so they come back approximately.
+    pub fn to_state(self) -> PadState {
+        let mut st = PadState::new();
+        st.buttons = self.hid;
+
+        st.touch_pressed = self.touch & TOUCH_PRESSED_FLAG != 0;
+        if st.touch_pressed {
+            let x = self.touch & 0xFFF;
+            let y = (self.touch >> 12) & 0xFFF;
+            st.touch_x = ((x * TOUCH_SCREEN_WIDTH as u32 + 0x7FF) / 0xFFF) as u16;
+            st.touch_y = ((y * TOUCH_SCREEN_HEIGHT as u32 + 0x7FF) / 0xFFF) as u16;
+        }
+
+        let x_cpad = (self.circle_pad & 0xFFF) as i32 - CPAD_CENTER_OFFSET_INT;
+        let y_cpad = ((self.circle_pad >> 12) & 0xFFF) as i32 - CPAD_CENTER_OFFSET_INT;
+        st.lx = x_cpad as f32 / CPAD_BOUND;
+        st.ly = y_cpad as f32 / CPAD_BOUND;
+
+        // Undo the 45° rotation: sum = rx + ry, diff = ry - rx.
+        let sum = (((self.cpp >> 16) & 0xFF) as i32 - CPP_CENTER_OFFSET_INT) as f32 / ROT_CPP_BOUND;
+        let diff = ((self.cpp >> 24) as i32 - CPP_CENTER_OFFSET_INT) as f32 / ROT_CPP_BOUND;
+        st.rx = (sum - diff) / 2.0;
+        st.ry = (sum + diff) / 2.0;
+
+        st.ir_buttons = ((self.cpp >> 8) & 0xFF) as u8;
+        st.interface_buttons = (self.interface & 0xFF) as u8;
+        st
+    }
+
+    /// Serialises the packet into its wire representation.
+    pub fn encode(self) -> [u8; PACKET_LEN] {
+        let mut buf = [0u8; PACKET_LEN];
+        let mut cursor = Cursor::new(&mut buf[..]);
+        // Writing into a buffer of exactly PACKET_LEN bytes cannot fail.
+        let _ = cursor.write_u32::<LittleEndian>(self.hid);
+        let _ = cursor.write_u32::<LittleEndian>(self.touch);
+        let _ = cursor.write_u32::<LittleEndian>(self.circle_pad);
+        let _ = cursor.write_u32::<LittleEndian>(self.cpp);
+        let _ = cursor.write_u32::<LittleEndian>(self.interface);
+        buf
+    }
+
+    /// Parses a packet from its wire representation.
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() != PACKET_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {PACKET_LEN} bytes, got {}", buf.len()),
+            ));
+        }
+        let mut cursor = Cursor::new(buf);
+        Ok(Self {
+            hid: cursor.read_u32::<LittleEndian>()?,
+            touch: cursor.read_u32::<LittleEndian>()?,
+            circle_pad: cursor.read_u32::<LittleEndian>()?,
+            cpp: cursor.read_u32::<LittleEndian>()?,
+            interface: cursor.read_u32::<LittleEndian>()?,
+        })
+    }
+}
+
+#[inline]
+fn clamp_u12(x: i32) -> u32 {
+    x.clamp(0, 0xFFF) as u32
+}
+
+#[inline]
+fn clamp_u8(x: i32) -> u32 {
+    x.clamp(0, 0xFF) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pad_state::DsButton;
+
+    /// Encodes `st` with centred sticks, so only the field under test varies.
+    fn encode_centred(mut st: PadState) -> [u8; PACKET_LEN] {
+        (st.lx, st.ly, st.rx, st.ry) = (0.0, 0.0, 0.0, 0.0);
+        Packet::from_state(&st).encode()
+    }
+
+    #[test]
+    fn neutral_state_golden_bytes() {
+        #[rustfmt::skip]
+        let expected = [
+            0xFF, 0x0F, 0x00, 0x00, // no HID button pressed (active low)
+            0x00, 0x00, 0x00, 0x02, // TOUCH_NONE
+            0x01, 0x18, 0x80, 0x00, // circle pad nudged just off 0x800
+            0x81, 0x00, 0x81, 0x80, // C-stick nudge, CPP_MAGIC
+            0x00, 0x00, 0x00, 0x00, // no interface button
+        ];
+        assert_eq!(Packet::from_state(&PadState::new()).encode(), expected);
+    }
+
+    #[test]
+    fn touch_golden_bytes() {
+        let mut st = PadState::new();
+        st.touch_pressed = true;
+        (st.touch_x, st.touch_y) = (160, 120);
+        #[rustfmt::skip]
+        let expected = [
+            0xFF, 0x0F, 0x00, 0x00,
+            0xFF, 0xF7, 0x7F, 0x01, // pressed flag, y = x = 0x7FF
+            0x00, 0x08, 0x80, 0x00,
+            0x81, 0x00, 0x80, 0x80,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(encode_centred(st), expected);
+    }
+
+    #[test]
+    fn home_and_power_golden_bytes() {
+        let mut st = PadState::new();
+        st.set_button(DsButton::Home, true);
+        st.set_button(DsButton::Power, true);
+        #[rustfmt::skip]
+        let expected = [
+            0xFF, 0x0F, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x02,
+            0x00, 0x08, 0x80, 0x00,
+            0x81, 0x00, 0x80, 0x80,
+            0x03, 0x00, 0x00, 0x00, // HOME | POWER
+        ];
+        assert_eq!(encode_centred(st), expected);
+    }
+
+    #[test]
+    fn zl_and_zr_golden_bytes() {
+        let mut st = PadState::new();
+        st.set_button(DsButton::ZL, true);
+        st.set_button(DsButton::ZR, true);
+        #[rustfmt::skip]
+        let expected = [
+            0xFF, 0x0F, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x02,
+            0x00, 0x08, 0x80, 0x00,
+            0x81, 0x06, 0x80, 0x80, // ZL | ZR in the IR byte
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(encode_centred(st), expected);
+    }
+
+    #[test]
+    fn decode_round_trips_encode() {
+        let mut st = PadState::new();
+        st.set_button(DsButton::A, true);
+        st.set_button(DsButton::ZR, true);
+        st.set_button(DsButton::Home, true);
+        (st.lx, st.ly, st.rx, st.ry) = (0.5, -1.0, -0.25, 0.75);
+        st.touch_pressed = true;
+        (st.touch_x, st.touch_y) = (12, 230);
+
+        let packet = Packet::from_state(&st);
+        assert_eq!(Packet::decode(&packet.encode()).unwrap(), packet);
+    }
+
+    #[test]
+    fn decode_rejects_short_buffer() {
+        let err = Packet::decode(&[0u8; PACKET_LEN - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn state_round_trips_through_packet() {
+        let mut st = PadState::new();
+        st.set_button(DsButton::B, true);
+        st.set_button(DsButton::DLeft, true);
+        st.set_button(DsButton::ZL, true);
+        st.set_button(DsButton::PowerLong, true);
+        (st.lx, st.ly, st.rx, st.ry) = (0.5, -1.0, -0.25, 0.75);
+        st.touch_pressed = true;
+        (st.touch_x, st.touch_y) = (319, 7);
+
+        let back = Packet::from_state(&st).to_state();
+        assert_eq!(back.buttons, st.buttons);
+        assert_eq!(back.ir_buttons, st.ir_buttons);
+        assert_eq!(back.interface_buttons, st.interface_buttons);
+        // One quantisation step of each field on the wire
+        let cpad_step = 1.0 / CPAD_BOUND;
+        let cstick_step = 1.0 / ROT_CPP_BOUND;
+        assert!((back.lx - st.lx).abs() <= cpad_step);
+        assert!((back.ly - st.ly).abs() <= cpad_step);
+        assert!((back.rx - st.rx).abs() <= cstick_step);
+        assert!((back.ry - st.ry).abs() <= cstick_step);
+        assert!(back.touch_pressed);
+        assert!(back.touch_x.abs_diff(st.touch_x) <= 1);
+        assert!(back.touch_y.abs_diff(st.touch_y) <= 1);
+    }
+
+    #[test]
+    fn touch_golden_bytes_decode() {
+        #[rustfmt::skip]
+        let bytes = [
+            0xFF, 0x0F, 0x00, 0x00,
+            0xFF, 0xF7, 0x7F, 0x01,
+            0x00, 0x08, 0x80, 0x00,
+            0x81, 0x00, 0x80, 0x80,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        let st = Packet::decode(&bytes).unwrap().to_state();
+        assert!(st.touch_pressed);
+        assert_eq!((st.touch_x, st.touch_y), (160, 120));
+        assert_eq!((st.lx, st.ly, st.rx, st.ry), (0.0, 0.0, 0.0, 0.0));
+        assert_eq!(st.buttons, 0xFFF);
+    }
+}
@@ -0,0 +1,75 @@
+// receiver.rs
+use std::{
+    io,
+    net::UdpSocket,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    constants::TARGET_PORT,
+    protocol::{PACKET_LEN, Packet},
+};
+
+/// Stand-in for the 3DS: listens on the input redirection port and prints
+/// every change in the decoded pad state.
+pub struct Receiver {
+    sock: UdpSocket,
+    running: Arc<AtomicBool>,
+}
+
+impl Receiver {
+    pub fn new(running: Arc<AtomicBool>) -> io::Result<Self> {
+        let sock = UdpSocket::bind(("0.0.0.0", TARGET_PORT))?;
+        // Wake up periodically so the `running` flag is honoured.
+        sock.set_read_timeout(Some(Duration::from_millis(200)))?;
+        Ok(Self { sock, running })
+    }
+
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        println!("3DS Input Redirection - Receiver");
+        println!("----------------------------------------");
+        println!("Listening on UDP port {TARGET_PORT}. Press Ctrl+C to stop.");
+
+        // Larger than a packet, so oversized datagrams are noticed rather than truncated.
+        let mut buf = [0u8; PACKET_LEN * 2];
+        let mut last: Option<Packet> = None;
+
+        while self.running.load(Ordering::SeqCst) {
+            let (len, from) = match self.sock.recv_from(&mut buf) {
+                Ok(r) => r,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::Interrupted
+                    ) =>
+                {
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let packet = match Packet::decode(&buf[..len]) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("Ignoring {len}-byte datagram from {from}: {e}");
+                    continue;
+                }
+            };
+
+            // The sender repeats its state continuously; only report changes.
+            if last != Some(packet) {
+                println!("[{from}] {}", packet.to_state());
+                last = Some(packet);
+            }
+        }
+
+        println!("\nStopped receiver.");
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+// session.rs
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    constants::RELEASE_REPEAT,
+    network::Sender,
+    pad_state::PadState,
+    protocol::{PACKET_LEN, Packet},
+};
+
+/// File signature and format version at the start of every session file.
+/// Each record after it is a little-endian `u64` of microseconds since the
+/// start of the session, followed by the packet exactly as it was sent.
+const MAGIC: &[u8; 8] = b"3DSIRSES";
+const VERSION: u8 = 1;
+
+/// Longest replay sleeps before re-checking the `running` flag.
+const MAX_REPLAY_WAIT: Duration = Duration::from_millis(50);
+
+/// One sent packet and when it was sent, relative to the start of the session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionRecord {
+    pub at: Duration,
+    pub packet: Packet,
+}
+
+/// Appends every sent packet to a session file.
+pub struct SessionWriter {
+    out: BufWriter<File>,
+    path: PathBuf,
+    started: Instant,
+}
+
+impl SessionWriter {
+    pub fn create(path: &Path, now: Instant) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_u8(VERSION)?;
+        Ok(Self {
+            out,
+            path: path.to_path_buf(),
+            started: now,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, packet: Packet, now: Instant) -> io::Result<()> {
+        let micros = now.duration_since(self.started).as_micros() as u64;
+        self.out.write_u64::<LittleEndian>(micros)?;
+        self.out.write_all(&packet.encode())
+    }
+
+    /// Flushes the file and returns its path.
+    pub fn finish(mut self) -> io::Result<PathBuf> {
+        self.out.flush()?;
+        Ok(self.path)
+    }
+}
+
+/// Reads every record of a session file.
+pub fn read_session(path: &Path) -> io::Result<Vec<SessionRecord>> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; MAGIC.len()];
+    input.read_exact(&mut magic)?;
+    let version = input.read_u8()?;
+    if &magic != MAGIC || version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a version {VERSION} session file", path.display()),
+        ));
+    }
+
+    let mut records = Vec::new();
+    loop {
+        let micros = match input.read_u64::<LittleEndian>() {
+            Ok(m) => m,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let mut buf = [0u8; PACKET_LEN];
+        input.read_exact(&mut buf)?;
+        records.push(SessionRecord {
+            at: Duration::from_micros(micros),
+            packet: Packet::decode(&buf)?,
+        });
+    }
+    Ok(records)
+}
+
+/// Sends `records` through `sender` with their original spacing divided by `speed`.
+/// Returns early once `running` is cleared. Like the live worker, a failed send is
+/// passed to `report` (once until a send succeeds again) and the replay goes on.
+pub fn replay(
+    records: &[SessionRecord],
+    sender: &mut Sender,
+    speed: f32,
+    running: &AtomicBool,
+    report: &mut impl FnMut(&io::Error),
+) {
+    let speed = speed.max(0.01);
+    let start = Instant::now();
+    let mut failing = false;
+    for record in records {
+        let due = start + record.at.div_f32(speed);
+        loop {
+            if !running.load(Ordering::SeqCst) {
+                return;
+            }
+            let now = Instant::now();
+            if now >= due {
+                break;
+            }
+            thread::sleep((due - now).min(MAX_REPLAY_WAIT));
+        }
+        match sender.send_packet(record.packet) {
+            Ok(()) => failing = false,
+            Err(e) if !failing => {
+                report(&e);
+                failing = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Replays a session file to `target_ip`, once or until `running` is cleared,
+/// then sends the neutral state so nothing stays held on the 3DS.
+/// Send failures go to `report` without stopping the replay.
+pub fn replay_file(
+    path: &Path,
+    target_ip: &str,
+    speed: f32,
+    looped: bool,
+    running: &AtomicBool,
+    mut report: impl FnMut(&io::Error),
+) -> anyhow::Result<()> {
+    let records = read_session(path)?;
+    // StartError can hold a gilrs error, which is not Sync, so keep only its message
+    let mut sender = Sender::new(target_ip).map_err(|e| anyhow::anyhow!("{e}"))?;
+
+    while running.load(Ordering::SeqCst) {
+        replay(&records, &mut sender, speed, running, &mut report);
+        if !looped || records.is_empty() {
+            break;
+        }
+    }
+
+    for _ in 0..RELEASE_REPEAT {
+        let _ = sender.send_state(&PadState::new());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A file path in the system temp dir, unique to this test run.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("irs-session-{}-{name}.irs", std::process::id()))
+    }
+
+    fn packet(buttons: u32) -> Packet {
+        let mut st = PadState::new();
+        st.buttons = buttons;
+        Packet::from_state(&st)
+    }
+
+    #[test]
+    fn written_session_reads_back() {
+        let path = temp_path("round-trip");
+        let start = Instant::now();
+        let mut writer = SessionWriter::create(&path, start).unwrap();
+        writer.write(packet(0xFFF), start).unwrap();
+        writer
+            .write(packet(0xFFE), start + Duration::from_micros(16_667))
+            .unwrap();
+        assert_eq!(writer.finish().unwrap(), path);
+
+        let records = read_session(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            records,
+            [
+                SessionRecord {
+                    at: Duration::ZERO,
+                    packet: packet(0xFFF),
+                },
+                SessionRecord {
+                    at: Duration::from_micros(16_667),
+                    packet: packet(0xFFE),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn header_only_session_is_empty() {
+        let path = temp_path("empty");
+        SessionWriter::create(&path, Instant::now())
+            .unwrap()
+            .finish()
+            .unwrap();
+        let records = read_session(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(records.unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_magic_and_version() {
+        let path = temp_path("bad-header");
+        let mut bad_magic = b"3DSIRSEX".to_vec();
+        bad_magic.push(VERSION);
+        fs::write(&path, &bad_magic).unwrap();
+        let err = read_session(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut bad_version = MAGIC.to_vec();
+        bad_version.push(VERSION + 1);
+        fs::write(&path, &bad_version).unwrap();
+        let err = read_session(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_truncated_record() {
+        let path = temp_path("truncated");
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&packet(0xFFF).encode()[..PACKET_LEN - 1]);
+        fs::write(&path, &bytes).unwrap();
+        let err = read_session(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,266 @@
+// stick.rs
+use serde::{Deserialize, Serialize};
+
+/// How the inner deadzone of a stick is shaped.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeadzoneShape {
+    /// Each axis is compared to the deadzone on its own (a square deadzone).
+    /// Values outside it are passed through unscaled.
+    #[default]
+    Axial,
+    /// The stick's distance from center is compared to the deadzone (a circular
+    /// deadzone), and the remaining range is rescaled to start at zero.
+    Radial,
+}
+
+/// How deflection past the deadzone is mapped to output deflection.
+/// Curves work on magnitudes in `0.0..=1.0`; the sign is restored afterwards.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub enum ResponseCurve {
+    /// Output equals input.
+    #[default]
+    Linear,
+    /// `input.powf(exponent)`: exponents above 1 give finer control near the center.
+    Exponential { exponent: f32 },
+    /// Piecewise-linear curve through `(input, output)` control points.
+    /// `(0, 0)` and `(1, 1)` are implied at the ends. Points may be listed in any
+    /// order; `sorted` must be applied before the curve is used.
+    Custom { points: Vec<(f32, f32)> },
+}
+
+impl ResponseCurve {
+    /// The same curve with its control points in input order, ready for `apply`.
+    pub fn sorted(&self) -> Self {
+        let mut curve = self.clone();
+        if let ResponseCurve::Custom { points } = &mut curve {
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        curve
+    }
+
+    pub fn apply(&self, magnitude: f32) -> f32 {
+        let m = magnitude.clamp(0.0, 1.0);
+        match self {
+            ResponseCurve::Linear => m,
+            ResponseCurve::Exponential { exponent } => m.powf(exponent.max(f32::EPSILON)),
+            ResponseCurve::Custom { points } => piecewise(points, m),
+        }
+    }
+}
+
+/// Interpolates `m` between the control points surrounding it.
+/// `points` must be sorted by input.
+fn piecewise(points: &[(f32, f32)], m: f32) -> f32 {
+    let mut prev = (0.0, 0.0);
+    for &next in points.iter().chain(&[(1.0, 1.0)]) {
+        if m <= next.0 {
+            let span = next.0 - prev.0;
+            if span <= f32::EPSILON {
+                return next.1;
+            }
+            return prev.1 + (next.1 - prev.1) * (m - prev.0) / span;
+        }
+        prev = next;
+    }
+    prev.1
+}
+
+/// Per-stick processing settings, applied to raw gilrs values before encoding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StickSettings {
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// Inner deadzone, as a fraction of full deflection.
+    pub deadzone: f32,
+    /// Outer deadzone: deflection beyond `1.0 - outer_deadzone` counts as full.
+    pub outer_deadzone: f32,
+    pub shape: DeadzoneShape,
+    /// Applied to the deflection left after the deadzones.
+    pub curve: ResponseCurve,
+    /// Multiplier on the curved deflection; the result is capped at full deflection.
+    pub sensitivity: f32,
+}
+
+impl StickSettings {
+    /// Turns raw stick values into the values to send.
+    /// Components that end up at rest are replaced by `nudge`, signed like the input.
+    pub fn process(&self, x: f32, y: f32, nudge: f32) -> (f32, f32) {
+        let x = if self.invert_x { -x } else { x };
+        let y = if self.invert_y { -y } else { y };
+        let outer = (1.0 - self.outer_deadzone).max(f32::EPSILON);
+
+        let (out_x, out_y) = match self.shape {
+            DeadzoneShape::Axial => (
+                self.respond(self.axial(x, outer)),
+                self.respond(self.axial(y, outer)),
+            ),
+            DeadzoneShape::Radial => {
+                let magnitude = x.hypot(y);
+                if magnitude < self.deadzone || magnitude <= f32::EPSILON {
+                    (0.0, 0.0)
+                } else {
+                    let span = (outer - self.deadzone).max(f32::EPSILON);
+                    let scaled = self.respond(((magnitude - self.deadzone) / span).min(1.0));
+                    (x / magnitude * scaled, y / magnitude * scaled)
+                }
+            }
+        };
+
+        (
+            at_rest_nudge(out_x, x, nudge),
+            at_rest_nudge(out_y, y, nudge),
+        )
+    }
+
+    /// Runs one signed deflection through the response curve and sensitivity.
+    /// A stick at rest stays at rest whatever the curve says.
+    fn respond(&self, value: f32) -> f32 {
+        if value == 0.0 {
+            return 0.0;
+        }
+        let out = (self.curve.apply(value.abs()) * self.sensitivity.max(0.0)).min(1.0);
+        out.copysign(value)
+    }
+
+    fn axial(&self, value: f32, outer: f32) -> f32 {
+        if value.abs() < self.deadzone {
+            0.0
+        } else {
+            (value / outer).clamp(-1.0, 1.0)
+        }
+    }
+}
+
+/// Replaces an at-rest output with a tiny deflection in the direction of the raw input.
+#[inline]
+fn at_rest_nudge(out: f32, raw: f32, nudge: f32) -> f32 {
+    if out != 0.0 {
+        out
+    } else if raw >= 0.0 {
+        nudge
+    } else {
+        -nudge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(shape: DeadzoneShape) -> StickSettings {
+        StickSettings {
+            invert_x: false,
+            invert_y: false,
+            deadzone: 0.2,
+            outer_deadzone: 0.1,
+            shape,
+            curve: ResponseCurve::Linear,
+            sensitivity: 1.0,
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn radial_deadzone_rescales_from_zero_and_keeps_direction() {
+        let s = settings(DeadzoneShape::Radial);
+        assert_eq!(s.process(0.1, -0.1, 0.0), (0.0, 0.0));
+
+        let (x, _) = s.process(0.2001, 0.0, 0.0);
+        assert!(x > 0.0 && x < 0.001);
+
+        // Halfway between the deadzone and the outer edge, diagonally
+        let m = 0.2 + 0.35;
+        let (x, y) = s.process(m / 2f32.sqrt(), -m / 2f32.sqrt(), 0.0);
+        assert!(close(x.hypot(y), 0.5));
+        assert!(close(x, -y) && x > 0.0);
+    }
+
+    #[test]
+    fn outer_deadzone_saturates() {
+        let (x, y) = settings(DeadzoneShape::Axial).process(0.95, -0.92, 0.0);
+        assert_eq!((x, y), (1.0, -1.0));
+        let (x, y) = settings(DeadzoneShape::Radial).process(0.0, -0.95, 0.0);
+        assert_eq!((x, y), (0.0, -1.0));
+    }
+
+    #[test]
+    fn at_rest_output_is_nudged_towards_the_input() {
+        let s = settings(DeadzoneShape::Axial);
+        assert_eq!(s.process(0.1, -0.1, 0.01), (0.01, -0.01));
+        assert_eq!(s.process(0.0, 0.0, 0.01), (0.01, 0.01));
+        // Inversion flips the direction of the nudge too
+        let s = StickSettings {
+            invert_x: true,
+            ..s
+        };
+        assert_eq!(s.process(0.1, 0.0, 0.01).0, -0.01);
+    }
+
+    #[test]
+    fn exponential_curve() {
+        let curve = ResponseCurve::Exponential { exponent: 2.0 };
+        assert!(close(curve.apply(0.5), 0.25));
+        assert!(close(curve.apply(1.0), 1.0));
+        // Exponents below zero are clamped, so the output stays within 0..=1
+        let curve = ResponseCurve::Exponential { exponent: -2.0 };
+        let out = curve.apply(0.5);
+        assert!(out <= 1.0 && close(out, 1.0));
+        assert_eq!(curve.apply(0.0), 0.0);
+    }
+
+    #[test]
+    fn custom_curve_interpolates_with_implied_ends() {
+        let curve = ResponseCurve::Custom {
+            points: vec![(0.5, 0.25)],
+        };
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert!(close(curve.apply(0.25), 0.125));
+        assert!(close(curve.apply(0.5), 0.25));
+        assert!(close(curve.apply(0.75), 0.625));
+        assert_eq!(curve.apply(1.0), 1.0);
+    }
+
+    #[test]
+    fn custom_curve_jumps_at_duplicate_inputs() {
+        let curve = ResponseCurve::Custom {
+            points: vec![(0.5, 0.2), (0.5, 0.8)],
+        };
+        assert!(close(curve.apply(0.5), 0.2));
+        assert!(close(curve.apply(0.6), 0.84));
+        assert!(curve.apply(0.5000001).is_finite());
+    }
+
+    #[test]
+    fn sorted_orders_custom_points() {
+        let curve = ResponseCurve::Custom {
+            points: vec![(0.8, 0.9), (0.2, 0.1), (0.5, 0.5)],
+        };
+        assert_eq!(
+            curve.sorted(),
+            ResponseCurve::Custom {
+                points: vec![(0.2, 0.1), (0.5, 0.5), (0.8, 0.9)],
+            }
+        );
+        assert_eq!(ResponseCurve::Linear.sorted(), ResponseCurve::Linear);
+    }
+
+    #[test]
+    fn sensitivity_is_capped_at_full_deflection() {
+        let s = StickSettings {
+            deadzone: 0.0,
+            outer_deadzone: 0.0,
+            sensitivity: 3.0,
+            ..settings(DeadzoneShape::Axial)
+        };
+        assert_eq!(s.process(0.5, -0.5, 0.0), (1.0, -1.0));
+        let (x, _) = StickSettings {
+            sensitivity: 0.5,
+            ..s
+        }
+        .process(0.5, 0.0, 0.0);
+        assert!(close(x, 0.25));
+    }
+}
@@ -0,0 +1,91 @@
+// touch.rs
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// A named point on the touchscreen that gamepad buttons can be mapped to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TouchHotspot {
+    pub name: String,
+    /// Position on the 320x240 bottom screen.
+    pub x: u16,
+    pub y: u16,
+    /// When set, a press taps the point for this long, however long the button is held.
+    /// Otherwise the point is touched for as long as the button is held.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hold_ms: Option<u64>,
+}
+
+impl TouchHotspot {
+    pub fn point(&self) -> (u16, u16) {
+        (self.x, self.y)
+    }
+}
+
+/// Timed touches started by hotspots with a hold duration.
+#[derive(Debug, Default)]
+pub struct Taps {
+    /// Point and end of each tap, oldest first.
+    running: Vec<((u16, u16), Instant)>,
+}
+
+impl Taps {
+    /// Starts tapping `hotspot` if it has a hold duration.
+    pub fn start(&mut self, hotspot: &TouchHotspot, now: Instant) {
+        if let Some(ms) = hotspot.hold_ms {
+            self.running
+                .push((hotspot.point(), now + Duration::from_millis(ms)));
+        }
+    }
+
+    /// The point of the latest tap still running.
+    pub fn current(&self) -> Option<(u16, u16)> {
+        self.running.last().map(|(point, _)| *point)
+    }
+
+    /// Drops the taps that are over; returns whether any were.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.running.len();
+        self.running.retain(|(_, until)| *until > now);
+        self.running.len() != before
+    }
+
+    pub fn reset(&mut self) {
+        self.running.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hotspot(x: u16, hold_ms: Option<u64>) -> TouchHotspot {
+        TouchHotspot {
+            name: format!("at {x}"),
+            x,
+            y: 10,
+            hold_ms,
+        }
+    }
+
+    #[test]
+    fn latest_tap_wins_until_it_expires() {
+        let now = Instant::now();
+        let mut taps = Taps::default();
+        taps.start(&hotspot(1, Some(100)), now);
+        taps.start(&hotspot(2, Some(50)), now);
+        assert_eq!(taps.current(), Some((2, 10)));
+
+        assert!(!taps.expire(now + Duration::from_millis(10)));
+        assert!(taps.expire(now + Duration::from_millis(50)));
+        assert_eq!(taps.current(), Some((1, 10)));
+        assert!(taps.expire(now + Duration::from_millis(100)));
+        assert_eq!(taps.current(), None);
+    }
+
+    #[test]
+    fn hotspots_without_hold_do_not_tap() {
+        let mut taps = Taps::default();
+        taps.start(&hotspot(1, None), Instant::now());
+        assert_eq!(taps.current(), None);
+    }
+}
@@ -0,0 +1,57 @@
+// trigger.rs
+
+/// Turns an analog trigger axis into a digital press.
+/// The gap between the two thresholds is the hysteresis band: a trigger resting
+/// near one threshold does not flicker between pressed and released.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerSettings {
+    /// Axis value at or above which a released trigger becomes pressed.
+    pub press_threshold: f32,
+    /// Axis value below which a pressed trigger becomes released.
+    pub release_threshold: f32,
+}
+
+impl TriggerSettings {
+    /// Returns whether the trigger is pressed after moving to `value`,
+    /// given whether it was pressed before.
+    pub fn update(&self, pressed: bool, value: f32) -> bool {
+        // A release threshold above the press threshold would leave the trigger stuck
+        let release = self.release_threshold.min(self.press_threshold);
+        if pressed {
+            value >= release
+        } else {
+            value >= self.press_threshold
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_and_releases_with_hysteresis() {
+        let t = TriggerSettings {
+            press_threshold: 0.5,
+            release_threshold: 0.4,
+        };
+        assert!(!t.update(false, 0.45));
+        assert!(t.update(false, 0.5));
+        // Inside the band the trigger keeps its previous state
+        assert!(t.update(true, 0.45));
+        assert!(!t.update(false, 0.45));
+        assert!(t.update(true, 0.4));
+        assert!(!t.update(true, 0.39));
+    }
+
+    #[test]
+    fn release_threshold_above_press_threshold_is_clamped() {
+        let t = TriggerSettings {
+            press_threshold: 0.5,
+            release_threshold: 0.8,
+        };
+        // Still held between the thresholds instead of releasing right after the press
+        assert!(t.update(true, 0.6));
+        assert!(!t.update(true, 0.49));
+    }
+}
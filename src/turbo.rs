@@ -0,0 +1,95 @@
+// turbo.rs
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::pad_state::{DsButton, PadState};
+
+/// Autofire settings for one 3DS button.
+/// The pattern is sampled once per send tick, so rates above half the send rate blur.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Turbo {
+    pub enabled: bool,
+    /// Press/release cycles per second.
+    pub rate_hz: f32,
+    /// Fraction of each cycle the button is held down.
+    pub duty_cycle: f32,
+}
+
+impl Default for Turbo {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rate_hz: 10.0,
+            duty_cycle: 0.5,
+        }
+    }
+}
+
+impl Turbo {
+    /// Whether the button is down after its source has been held for `held_for`.
+    /// Every pattern starts with a press, so a quick tap still registers.
+    pub fn is_down(&self, held_for: Duration) -> bool {
+        // The start is down even at a duty cycle of 0, which would otherwise never press
+        if self.rate_hz <= 0.0 || held_for.is_zero() {
+            return true;
+        }
+        (held_for.as_secs_f32() * self.rate_hz).fract() < self.duty_cycle.clamp(0.0, 1.0)
+    }
+}
+
+/// Tracks how long each turbo button has been held, to place it in its pattern.
+#[derive(Debug, Default)]
+pub struct Autofire {
+    pressed_since: BTreeMap<DsButton, Instant>,
+}
+
+impl Autofire {
+    /// Releases every enabled turbo button of `st` that is in the off part of its pattern.
+    pub fn apply(&mut self, turbo: &BTreeMap<DsButton, Turbo>, st: &mut PadState, now: Instant) {
+        for (&button, settings) in turbo {
+            if !settings.enabled || !st.is_pressed(button) {
+                self.pressed_since.remove(&button);
+                continue;
+            }
+            let since = *self.pressed_since.entry(button).or_insert(now);
+            if !settings.is_down(now - since) {
+                st.set_button(button, false);
+            }
+        }
+    }
+
+    /// Forgets every pattern, so the next press starts a fresh one.
+    pub fn reset(&mut self) {
+        self.pressed_since.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_follows_rate_and_duty_cycle() {
+        let turbo = Turbo::default(); // 10 Hz, half the cycle down
+        let at = |ms| turbo.is_down(Duration::from_millis(ms));
+        assert!(at(0));
+        assert!(at(25));
+        assert!(!at(50));
+        assert!(!at(75));
+        assert!(at(110));
+    }
+
+    #[test]
+    fn zero_duty_cycle_still_presses_once() {
+        let turbo = Turbo {
+            duty_cycle: 0.0,
+            ..Turbo::default()
+        };
+        assert!(turbo.is_down(Duration::ZERO));
+        assert!(!turbo.is_down(Duration::from_millis(25)));
+    }
+}